@@ -0,0 +1,89 @@
+use regex::Regex;
+use std::fs::Metadata;
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+use walkdir::WalkDir;
+
+use crate::config::units::{format_duration, parse_duration};
+use crate::config::{AgeConfig, AgeScope, AgeTimestamp};
+
+/// Compiled form of `AgeConfig`, shared by the scanning tasks.
+pub struct AgePolicy {
+    min_age: Option<Duration>,
+    rules: Vec<(Regex, Duration)>,
+    scope: AgeScope,
+    timestamp: AgeTimestamp,
+}
+
+impl AgePolicy {
+    /// Expects a validated configuration.
+    pub fn new(config: &AgeConfig) -> Self {
+        Self {
+            min_age: config.min_age.as_deref().map(|age| parse_duration(age).unwrap()),
+            rules: config
+                .rules
+                .iter()
+                .map(|rule| (Regex::new(&rule.pattern).unwrap(), parse_duration(&rule.min_age).unwrap()))
+                .collect(),
+            scope: config.scope,
+            timestamp: config.timestamp,
+        }
+    }
+
+    fn min_age_for(&self, relative_path: &Path) -> Option<Duration> {
+        let path_str = relative_path.to_string_lossy();
+        self.rules
+            .iter()
+            .filter(|(pattern, _)| pattern.is_match(&path_str))
+            .map(|(_, age)| *age)
+            .max()
+            .or(self.min_age)
+    }
+
+    fn timestamp_of(&self, metadata: &Metadata) -> Option<SystemTime> {
+        match self.timestamp {
+            AgeTimestamp::Mtime => metadata.modified().ok(),
+            AgeTimestamp::Atime => metadata.accessed().ok(),
+            AgeTimestamp::Newest => metadata.modified().ok().max(metadata.accessed().ok()),
+        }
+    }
+
+    /// Most recent use of anything under `root`, skipping `excluded` and `.git`.
+    pub(super) fn last_used(&self, root: &Path, excluded: Option<&Path>) -> Option<SystemTime> {
+        WalkDir::new(root)
+            .into_iter()
+            .filter_entry(|entry| {
+                Some(entry.path()) != excluded && entry.file_name() != ".git"
+            })
+            .filter_map(Result::ok)
+            .filter_map(|entry| entry.metadata().ok())
+            .filter_map(|metadata| self.timestamp_of(&metadata))
+            .max()
+    }
+
+    /// Why `path` is too recently used to be cleaned, if it is.
+    pub fn check(&self, path: &Path, relative_path: &Path) -> Option<String> {
+        let min_age = self.min_age_for(relative_path)?;
+
+        let last_used = match self.scope {
+            AgeScope::Artifact => self.last_used(path, None),
+            AgeScope::Project => self.last_used(path.parent()?, Some(path)),
+        }?;
+
+        let age = SystemTime::now().duration_since(last_used).unwrap_or_default();
+        if age >= min_age {
+            return None;
+        }
+
+        let subject = match self.scope {
+            AgeScope::Artifact => "used",
+            AgeScope::Project => "project used",
+        };
+        Some(format!(
+            "{} {} ago, younger than {}",
+            subject,
+            format_duration(age),
+            format_duration(min_age)
+        ))
+    }
+}
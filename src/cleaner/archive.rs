@@ -0,0 +1,230 @@
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read};
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+use super::attributes::{clear, from_hex, to_hex, Attributes, HardLinks};
+use super::checksum::{self, Digest, TreeEntry};
+use super::store::EntryKind;
+use super::types::BackupEntry;
+
+/// File extension of compressed backup archives, one per cleanup run.
+pub const EXTENSION: &str = "tar.zst";
+
+const COMPRESSION_LEVEL: i32 = 3;
+
+/// PAX record prefix of extended attributes, as GNU tar and bsdtar write them.
+const XATTR_PREFIX: &str = "SCHILY.xattr.";
+
+/// Stream `items` (source path, name inside the archive) into a new
+/// zstd-compressed tarball at `path`, which must not exist yet. Symlinks are stored as symlinks and hard
+/// links as links; nanosecond times and extended attributes go into PAX
+/// records next to the usual mode and owner.
+pub fn write(path: &Path, items: &[(PathBuf, PathBuf)]) -> io::Result<()> {
+    let file = File::create_new(path)?;
+    let encoder = zstd::Encoder::new(BufWriter::new(file), COMPRESSION_LEVEL)?;
+    let mut builder = tar::Builder::new(encoder);
+    for (source, name) in items {
+        // Links only within an item, so every item can be restored on its own
+        let mut links = HardLinks::default();
+        for entry in WalkDir::new(source).follow_root_links(false) {
+            let entry = entry?;
+            let relative = entry.path().strip_prefix(source).expect("walked paths are below their root");
+            // Joining an empty path would add a trailing `/`
+            let entry_name = if relative.as_os_str().is_empty() { name.clone() } else { name.join(relative) };
+            let attributes = Attributes::read(entry.path())?;
+            let metadata = entry.path().symlink_metadata()?;
+
+            let mut records = vec![
+                ("mtime".to_string(), format!("{}.{:09}", attributes.mtime.0, attributes.mtime.1).into_bytes()),
+                ("atime".to_string(), format!("{}.{:09}", attributes.atime.0, attributes.atime.1).into_bytes()),
+            ];
+            for (key, value) in &attributes.xattrs {
+                records.push((format!("{}{}", XATTR_PREFIX, key), from_hex(value)));
+            }
+            builder.append_pax_extensions(records.iter().map(|(key, value)| (key.as_str(), value.as_slice())))?;
+
+            let mut header = tar::Header::new_gnu();
+            header.set_metadata_in_mode(&metadata, tar::HeaderMode::Complete);
+            if metadata.is_symlink() {
+                builder.append_link(&mut header, &entry_name, fs::read_link(entry.path())?)?;
+            } else if let Some(first) = links.first(&metadata, &entry_name) {
+                header.set_entry_type(tar::EntryType::Link);
+                header.set_size(0);
+                builder.append_link(&mut header, &entry_name, first)?;
+            } else if metadata.is_file() {
+                // Only as much as the header announces, should the file grow meanwhile
+                let contents = File::open(entry.path())?.take(metadata.len());
+                builder.append_data(&mut header, &entry_name, contents)?;
+            } else {
+                builder.append_data(&mut header, &entry_name, io::empty())?;
+            }
+        }
+    }
+
+    let writer = builder.into_inner()?.finish()?;
+    writer.into_inner().map_err(|e| e.into_error())?.sync_all()
+}
+
+pub fn list(path: &Path) -> io::Result<Vec<BackupEntry>> {
+    let mut archive = open(path)?;
+    let mut entries = Vec::new();
+    for entry in archive.entries()? {
+        let entry = entry?;
+        entries.push(BackupEntry {
+            // Re-collect the components to drop the trailing `/` of directories
+            path: entry.path()?.components().collect(),
+            is_directory: entry.header().entry_type().is_dir(),
+            size: entry.header().size()?,
+        });
+    }
+    Ok(entries)
+}
+
+/// Unpack every entry `destination_of` maps to a path there with its
+/// attributes, returning how many were written.
+pub fn extract(path: &Path, destination_of: impl Fn(&Path) -> Option<PathBuf>) -> io::Result<usize> {
+    let mut archive = open(path)?;
+    let mut restored: HashMap<PathBuf, PathBuf> = HashMap::new();
+    let mut unpacked = Vec::new();
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let entry_path: PathBuf = entry.path()?.components().collect();
+        check_relative(&entry_path)?;
+        let destination = match destination_of(&entry_path) {
+            Some(destination) => destination,
+            None => continue,
+        };
+        if let Some(parent) = destination.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let attributes = attributes(&mut entry)?;
+        let entry_type = entry.header().entry_type();
+        if entry_type.is_dir() {
+            if destination.symlink_metadata().is_ok_and(|existing| !existing.is_dir()) {
+                fs::remove_file(&destination)?;
+            }
+            fs::create_dir_all(&destination)?;
+        } else if entry_type.is_hard_link() {
+            let first: PathBuf = entry.link_name()?.unwrap_or_default().components().collect();
+            let first = restored.get(&first).ok_or_else(|| {
+                io::Error::new(io::ErrorKind::InvalidData, format!("{} links to a missing entry", entry_path.display()))
+            })?;
+            clear(&destination)?;
+            fs::hard_link(first, &destination)?;
+        } else {
+            clear(&destination)?;
+            entry.unpack(&destination)?;
+        }
+        restored.insert(entry_path, destination.clone());
+        unpacked.push((destination, attributes));
+    }
+
+    // In reverse, as in `attributes::copy`
+    for (destination, attributes) in unpacked.iter().rev() {
+        attributes.apply(destination)?;
+    }
+    Ok(unpacked.len())
+}
+
+/// Attributes of an archive entry from its header and PAX records.
+fn attributes<R: Read>(entry: &mut tar::Entry<R>) -> io::Result<Attributes> {
+    let header = entry.header();
+    let mtime = (header.mtime()? as i64, 0);
+    let mut attributes = Attributes {
+        mode: header.mode()? & 0o7777,
+        uid: header.uid()? as u32,
+        gid: header.gid()? as u32,
+        mtime,
+        atime: mtime,
+        ..Attributes::default()
+    };
+
+    if let Some(records) = entry.pax_extensions()? {
+        for record in records {
+            let record = record?;
+            let key = match record.key() {
+                Ok(key) => key,
+                Err(_) => continue,
+            };
+            match key {
+                "mtime" => attributes.mtime = parse_time(record.value_bytes()).unwrap_or(attributes.mtime),
+                "atime" => attributes.atime = parse_time(record.value_bytes()).unwrap_or(attributes.atime),
+                _ => {
+                    if let Some(name) = key.strip_prefix(XATTR_PREFIX) {
+                        attributes.xattrs.insert(name.to_string(), to_hex(record.value_bytes()));
+                    }
+                }
+            }
+        }
+    }
+    Ok(attributes)
+}
+
+/// A PAX time, `<seconds>[.<fraction>]`.
+fn parse_time(value: &[u8]) -> Option<(i64, u32)> {
+    let value = std::str::from_utf8(value).ok()?;
+    let (seconds, fraction) = value.split_once('.').unwrap_or((value, ""));
+    // Pad or cut the fraction to nanoseconds
+    let nanoseconds = format!("{:0<9}", fraction).get(..9)?.parse().ok()?;
+    Some((seconds.parse().ok()?, nanoseconds))
+}
+
+/// Digests of `items` (names inside the archive) as stored in the archive at
+/// `path`. Items it does not contain are left out.
+pub fn digests(path: &Path, items: &[PathBuf]) -> io::Result<HashMap<PathBuf, Digest>> {
+    let mut entries: HashMap<PathBuf, Vec<TreeEntry>> = HashMap::new();
+    let mut files: HashMap<PathBuf, (u64, String)> = HashMap::new();
+    let mut archive = open(path)?;
+    for entry in archive.entries()? {
+        let entry = entry?;
+        let entry_path: PathBuf = entry.path()?.components().collect();
+        let item = match items.iter().find(|item| entry_path.starts_with(item)) {
+            Some(item) => item,
+            None => continue,
+        };
+        let relative = entry_path.strip_prefix(item).expect("matched by prefix").to_path_buf();
+
+        let entry_type = entry.header().entry_type();
+        let tree_entry = if entry_type.is_hard_link() {
+            // The same contents as the file it links to
+            let first: PathBuf = entry.link_name()?.unwrap_or_default().components().collect();
+            let (size, content) = files.get(&first).cloned().unwrap_or_default();
+            TreeEntry { relative, kind: EntryKind::File, size, content }
+        } else if entry_type.is_dir() {
+            TreeEntry { relative, kind: EntryKind::Directory, size: 0, content: String::new() }
+        } else if entry_type.is_symlink() {
+            let link_target = entry.link_name()?.unwrap_or_default().to_string_lossy().into_owned();
+            TreeEntry { relative, kind: EntryKind::Symlink, size: 0, content: link_target }
+        } else {
+            let size = entry.header().size()?;
+            let content = checksum::reader(entry)?;
+            files.insert(entry_path.clone(), (size, content.clone()));
+            TreeEntry { relative, kind: EntryKind::File, size, content }
+        };
+        entries.entry(item.clone()).or_default().push(tree_entry);
+    }
+
+    Ok(entries
+        .into_iter()
+        .map(|(item, entries)| (item, checksum::combine(entries)))
+        .collect())
+}
+
+/// Refuse stored paths that could point outside where they are restored to.
+pub(super) fn check_relative(path: &Path) -> io::Result<()> {
+    if path.components().all(|component| matches!(component, std::path::Component::Normal(_))) {
+        Ok(())
+    } else {
+        Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("unsafe path in backup: {}", path.display()),
+        ))
+    }
+}
+
+fn open(path: &Path) -> io::Result<tar::Archive<zstd::Decoder<'static, BufReader<File>>>> {
+    Ok(tar::Archive::new(zstd::Decoder::new(File::open(path)?)?))
+}
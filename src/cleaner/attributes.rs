@@ -0,0 +1,197 @@
+use filetime::FileTime;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, Metadata};
+use std::io;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// Everything about a path besides its contents that backups keep.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct Attributes {
+    /// Permission bits, including setuid, setgid and sticky.
+    pub mode: u32,
+    pub uid: u32,
+    pub gid: u32,
+    /// Seconds since the Unix epoch and nanoseconds.
+    pub mtime: (i64, u32),
+    pub atime: (i64, u32),
+    /// Extended attributes by name, values in hex.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub xattrs: BTreeMap<String, String>,
+}
+
+impl Attributes {
+    /// Attributes of `path` itself, not of what it links to.
+    pub fn read(path: &Path) -> io::Result<Self> {
+        let metadata = path.symlink_metadata()?;
+        let mtime = FileTime::from_last_modification_time(&metadata);
+        let atime = FileTime::from_last_access_time(&metadata);
+        let mut attributes = Self {
+            mtime: (mtime.unix_seconds(), mtime.nanoseconds()),
+            atime: (atime.unix_seconds(), atime.nanoseconds()),
+            ..Self::default()
+        };
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+            attributes.mode = metadata.mode() & 0o7777;
+            attributes.uid = metadata.uid();
+            attributes.gid = metadata.gid();
+            // Filesystems without extended attributes simply have none
+            if let Ok(names) = xattr::list(path) {
+                for name in names {
+                    if let (Some(key), Ok(Some(value))) = (name.to_str(), xattr::get(path, &name)) {
+                        attributes.xattrs.insert(key.to_string(), to_hex(&value));
+                    }
+                }
+            }
+        }
+        #[cfg(not(unix))]
+        {
+            attributes.mode = if metadata.permissions().readonly() { 0o444 } else { 0o644 };
+        }
+
+        Ok(attributes)
+    }
+
+    /// Give `path` these attributes. Ownership only changes when running as
+    /// root, and extended attributes the destination cannot hold are dropped.
+    pub fn apply(&self, path: &Path) -> io::Result<()> {
+        let is_symlink = path.symlink_metadata()?.file_type().is_symlink();
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            if unsafe { libc::geteuid() } == 0 {
+                std::os::unix::fs::lchown(path, Some(self.uid), Some(self.gid))?;
+            }
+            for (name, value) in &self.xattrs {
+                match xattr::set(path, name, &from_hex(value)) {
+                    // Unsupported by the filesystem, or a namespace only root may write
+                    Err(e) if e.raw_os_error() == Some(libc::ENOTSUP) || e.kind() == io::ErrorKind::PermissionDenied => {}
+                    result => result?,
+                }
+            }
+            // After chown, which clears the setuid and setgid bits
+            if !is_symlink {
+                fs::set_permissions(path, fs::Permissions::from_mode(self.mode))?;
+            }
+        }
+        #[cfg(not(unix))]
+        if !is_symlink {
+            let mut permissions = path.metadata()?.permissions();
+            permissions.set_readonly(self.mode & 0o222 == 0);
+            fs::set_permissions(path, permissions)?;
+        }
+
+        filetime::set_symlink_file_times(
+            path,
+            FileTime::from_unix_time(self.atime.0, self.atime.1),
+            FileTime::from_unix_time(self.mtime.0, self.mtime.1),
+        )
+    }
+}
+
+/// Remembers the files with several hard links, so later links to the same
+/// file can be recreated as links.
+#[derive(Default)]
+pub struct HardLinks {
+    seen: HashMap<(u64, u64), PathBuf>,
+}
+
+impl HardLinks {
+    /// Where the file described by `metadata` was first seen, or `None` after
+    /// recording it as `path` if this is the first time.
+    pub fn first(&mut self, metadata: &Metadata, path: &Path) -> Option<PathBuf> {
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+            if metadata.is_file() && metadata.nlink() > 1 {
+                let key = (metadata.dev(), metadata.ino());
+                if let Some(first) = self.seen.get(&key) {
+                    return Some(first.clone());
+                }
+                self.seen.insert(key, path.to_path_buf());
+            }
+        }
+        #[cfg(not(unix))]
+        let _ = (metadata, path);
+        None
+    }
+}
+
+/// Copy the file, directory or symlink at `source` to `destination` with its
+/// attributes, keeping symlinks as symlinks and hard links between the copied
+/// files. Whatever is in the way at the destination is replaced.
+pub fn copy(source: &Path, destination: &Path) -> io::Result<()> {
+    if let Some(parent) = destination.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let mut copied = Vec::new();
+    let mut links = HardLinks::default();
+    for entry in WalkDir::new(source).follow_root_links(false) {
+        let entry = entry?;
+        let relative = entry.path().strip_prefix(source).expect("walked paths are below their root");
+        // Joining an empty path would add a trailing `/`
+        let target = if relative.as_os_str().is_empty() {
+            destination.to_path_buf()
+        } else {
+            destination.join(relative)
+        };
+        // Before reading the contents, which may touch the access time
+        let attributes = Attributes::read(entry.path())?;
+        let metadata = entry.path().symlink_metadata()?;
+
+        if metadata.is_dir() {
+            if target.symlink_metadata().is_ok_and(|existing| !existing.is_dir()) {
+                fs::remove_file(&target)?;
+            }
+            fs::create_dir_all(&target)?;
+        } else {
+            clear(&target)?;
+            if metadata.is_symlink() {
+                symlink(&fs::read_link(entry.path())?, &target)?;
+            } else if let Some(first) = links.first(&metadata, &target) {
+                fs::hard_link(first, &target)?;
+            } else {
+                fs::copy(entry.path(), &target)?;
+            }
+        }
+        copied.push((target, attributes));
+    }
+
+    // Deepest first, so filling a directory does not change its times afterwards
+    for (target, attributes) in copied.iter().rev() {
+        attributes.apply(target)?;
+    }
+    Ok(())
+}
+
+/// Remove whatever is at `path` so a file or symlink can be created there.
+pub(super) fn clear(path: &Path) -> io::Result<()> {
+    match path.symlink_metadata() {
+        Ok(metadata) if metadata.is_dir() => fs::remove_dir_all(path),
+        Ok(_) => fs::remove_file(path),
+        Err(_) => Ok(()),
+    }
+}
+
+pub(super) fn symlink(link_target: &Path, path: &Path) -> io::Result<()> {
+    #[cfg(unix)]
+    return std::os::unix::fs::symlink(link_target, path);
+    #[cfg(not(unix))]
+    return fs::copy(link_target, path).map(|_| ());
+}
+
+pub(super) fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+pub(super) fn from_hex(hex: &str) -> Vec<u8> {
+    (0..hex.len() / 2)
+        .filter_map(|index| u8::from_str_radix(&hex[index * 2..index * 2 + 2], 16).ok())
+        .collect()
+}
@@ -0,0 +1,153 @@
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+use super::store::EntryKind;
+
+/// What an item holds, to tell whether two copies of it are the same.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Digest {
+    /// Bytes in its files.
+    pub size: u64,
+    pub checksum: String,
+}
+
+/// One path of an item, relative to the item, as it goes into its digest.
+pub struct TreeEntry {
+    pub relative: PathBuf,
+    pub kind: EntryKind,
+    pub size: u64,
+    /// Content hash of a file or target of a symlink; empty for directories.
+    pub content: String,
+}
+
+/// BLAKE3 hash of a file's contents, as hex.
+pub fn file(path: &Path) -> io::Result<String> {
+    reader(open(path)?)
+}
+
+/// Open without updating the access time where the platform allows it, so
+/// hashing an item before backing it up does not change what gets kept.
+#[cfg(target_os = "linux")]
+fn open(path: &Path) -> io::Result<File> {
+    use std::os::unix::fs::OpenOptionsExt;
+
+    // O_NOATIME is refused for files the user does not own
+    std::fs::OpenOptions::new()
+        .read(true)
+        .custom_flags(libc::O_NOATIME)
+        .open(path)
+        .or_else(|_| File::open(path))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn open(path: &Path) -> io::Result<File> {
+    File::open(path)
+}
+
+pub fn reader(reader: impl Read) -> io::Result<String> {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update_reader(reader)?;
+    Ok(hasher.finalize().to_hex().to_string())
+}
+
+/// Digest of an item from its entries, in any order. A lone file hashes to
+/// its contents' hash; a tree hashes every path with its type and contents
+/// in name order, so two trees hash the same exactly when they hold the same
+/// things.
+pub fn combine(mut entries: Vec<TreeEntry>) -> Digest {
+    let size = entries.iter().map(|entry| entry.size).sum();
+    if let [entry] = entries.as_slice() {
+        if entry.kind == EntryKind::File && entry.relative.as_os_str().is_empty() {
+            return Digest { size, checksum: entry.content.clone() };
+        }
+    }
+
+    // Component order is the order of a sorted depth-first walk
+    entries.sort_by(|a, b| a.relative.cmp(&b.relative));
+    let mut hasher = blake3::Hasher::new();
+    for entry in &entries {
+        hasher.update(entry.relative.to_string_lossy().as_bytes());
+        hasher.update(b"\0");
+        hasher.update(match entry.kind {
+            EntryKind::File => b"file\0".as_slice(),
+            EntryKind::Directory => b"directory\0",
+            EntryKind::Symlink => b"symlink\0",
+        });
+        hasher.update(entry.content.as_bytes());
+        hasher.update(b"\n");
+    }
+    Digest { size, checksum: hasher.finalize().to_hex().to_string() }
+}
+
+/// Digest of the file, directory or symlink at `path`.
+pub fn item(path: &Path) -> io::Result<Digest> {
+    let mut entries = Vec::new();
+    for entry in WalkDir::new(path).follow_root_links(false) {
+        let entry = entry?;
+        let relative = entry.path().strip_prefix(path).expect("walked paths are below their root");
+        let file_type = entry.file_type();
+        entries.push(if file_type.is_symlink() {
+            TreeEntry {
+                relative: relative.to_path_buf(),
+                kind: EntryKind::Symlink,
+                size: 0,
+                content: std::fs::read_link(entry.path())?.to_string_lossy().into_owned(),
+            }
+        } else if file_type.is_dir() {
+            TreeEntry { relative: relative.to_path_buf(), kind: EntryKind::Directory, size: 0, content: String::new() }
+        } else {
+            TreeEntry {
+                relative: relative.to_path_buf(),
+                kind: EntryKind::File,
+                size: entry.metadata()?.len(),
+                content: file(entry.path())?,
+            }
+        });
+    }
+    Ok(combine(entries))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(relative: &str, kind: EntryKind, size: u64, content: &str) -> TreeEntry {
+        TreeEntry { relative: PathBuf::from(relative), kind, size, content: content.to_string() }
+    }
+
+    #[test]
+    fn a_lone_file_hashes_to_its_contents() {
+        let content = reader(&b"hello"[..]).unwrap();
+        let digest = combine(vec![entry("", EntryKind::File, 5, &content)]);
+        assert_eq!(digest, Digest { size: 5, checksum: content });
+    }
+
+    #[test]
+    fn trees_hash_the_same_in_any_order() {
+        let tree = || {
+            vec![
+                entry("", EntryKind::Directory, 0, ""),
+                entry("a", EntryKind::File, 3, "aaa"),
+                entry("b/c", EntryKind::File, 4, "cccc"),
+                entry("b", EntryKind::Directory, 0, ""),
+            ]
+        };
+        let mut reversed = tree();
+        reversed.reverse();
+        assert_eq!(combine(tree()), combine(reversed));
+        assert_eq!(combine(tree()).size, 7);
+    }
+
+    #[test]
+    fn names_kinds_and_contents_all_count() {
+        let base = combine(vec![entry("", EntryKind::Directory, 0, ""), entry("a", EntryKind::File, 0, "x")]);
+        let renamed = combine(vec![entry("", EntryKind::Directory, 0, ""), entry("b", EntryKind::File, 0, "x")]);
+        let symlink = combine(vec![entry("", EntryKind::Directory, 0, ""), entry("a", EntryKind::Symlink, 0, "x")]);
+        let changed = combine(vec![entry("", EntryKind::Directory, 0, ""), entry("a", EntryKind::File, 0, "y")]);
+        assert_ne!(base, renamed);
+        assert_ne!(base, symlink);
+        assert_ne!(base, changed);
+    }
+}
@@ -0,0 +1,232 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use walkdir::WalkDir;
+
+use crate::config::units::format_duration;
+use crate::config::GitConfig;
+
+/// Entries git keeps in its directory while an operation waits for the user.
+const OPERATIONS: &[(&str, &str)] = &[
+    ("rebase-merge", "rebase"),
+    ("rebase-apply", "rebase"),
+    ("MERGE_HEAD", "merge"),
+    ("BISECT_LOG", "bisect"),
+    ("CHERRY_PICK_HEAD", "cherry-pick"),
+    ("REVERT_HEAD", "revert"),
+];
+
+/// What git knows about one repository: the paths in its index and the
+/// untracked paths its excludes ignore.
+struct Repository {
+    tracked: BTreeSet<PathBuf>,
+    ignored: HashSet<PathBuf>,
+}
+
+impl Repository {
+    fn load(root: &Path) -> Result<Self, String> {
+        Ok(Self {
+            tracked: ls_files(root, &[])?.collect(),
+            // `--exclude-standard` covers .gitignore, .git/info/exclude and core.excludesFile
+            ignored: ls_files(root, &["--others", "--ignored", "--exclude-standard", "--directory"])?.collect(),
+        })
+    }
+
+    /// Whether `relative_path` or anything below it is in the index.
+    fn tracks(&self, relative_path: &Path) -> bool {
+        // Paths sort component-wise, so descendants directly follow their parent
+        self.tracked
+            .range(relative_path.to_path_buf()..)
+            .next()
+            .is_some_and(|tracked| tracked.starts_with(relative_path))
+    }
+
+    fn ignores(&self, relative_path: &Path) -> bool {
+        relative_path
+            .ancestors()
+            .filter(|ancestor| !ancestor.as_os_str().is_empty())
+            .any(|ancestor| self.ignored.contains(ancestor))
+    }
+}
+
+fn ls_files(root: &Path, args: &[&str]) -> Result<impl Iterator<Item = PathBuf>, String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(root)
+        .args(["ls-files", "-z"])
+        .args(args)
+        .output()
+        .map_err(|e| format!("cannot run git: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "git ls-files failed in {}: {}",
+            root.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout).into_owned();
+    let paths: Vec<PathBuf> = stdout
+        .split('\0')
+        .filter(|path| !path.is_empty())
+        .map(|path| PathBuf::from(path.trim_end_matches('/')))
+        .collect();
+    Ok(paths.into_iter())
+}
+
+/// Closest ancestor of `path` (excluding itself) holding a `.git` entry.
+pub fn repository_root(path: &Path) -> Option<&Path> {
+    path.ancestors()
+        .skip(1)
+        .find(|ancestor| ancestor.join(".git").exists())
+}
+
+/// Only lets through paths git ignores and that contain nothing tracked.
+/// Each repository is queried once and shared by the scanning tasks.
+#[derive(Default)]
+pub struct GitFilter {
+    repositories: Mutex<HashMap<PathBuf, Arc<Result<Repository, String>>>>,
+}
+
+impl GitFilter {
+    fn repository(&self, root: &Path) -> Arc<Result<Repository, String>> {
+        let mut repositories = self.repositories.lock().unwrap();
+        Arc::clone(
+            repositories
+                .entry(root.to_path_buf())
+                .or_insert_with(|| Arc::new(Repository::load(root))),
+        )
+    }
+
+    /// Why git would not consider `path` disposable, if it would not.
+    pub fn check(&self, path: &Path) -> Option<String> {
+        let root = match repository_root(path) {
+            Some(root) => root,
+            None => return Some("not inside a git repository".to_string()),
+        };
+        let repository = self.repository(root);
+        let repository = match repository.as_ref() {
+            Ok(repository) => repository,
+            Err(e) => return Some(e.clone()),
+        };
+        let relative_path = path.strip_prefix(root).ok()?;
+
+        if repository.tracks(relative_path) {
+            Some("tracked by git".to_string())
+        } else if !repository.ignores(relative_path) {
+            Some("not ignored by git".to_string())
+        } else {
+            None
+        }
+    }
+}
+
+/// The git directory of the repository at `root`; `.git` may be a
+/// `gitdir:` file for worktrees and submodules.
+fn git_dir(root: &Path) -> Option<PathBuf> {
+    let dot_git = root.join(".git");
+    if dot_git.is_dir() {
+        return Some(dot_git);
+    }
+    let contents = std::fs::read_to_string(&dot_git).ok()?;
+    let dir = contents.trim().strip_prefix("gitdir:")?.trim();
+    Some(root.join(dir))
+}
+
+/// Unfinished work in one repository.
+struct RepositoryState {
+    operation: Option<&'static str>,
+    last_commit: Option<SystemTime>,
+}
+
+impl RepositoryState {
+    fn load(root: &Path) -> Self {
+        let operation = git_dir(root).and_then(|dir| {
+            OPERATIONS
+                .iter()
+                .find(|(entry, _)| dir.join(entry).exists())
+                .map(|(_, operation)| *operation)
+        });
+
+        // No commits yet, or no git at all: there is nothing to compare against
+        let last_commit = Command::new("git")
+            .arg("-C")
+            .arg(root)
+            .args(["log", "-1", "--format=%ct"])
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+            .and_then(|output| String::from_utf8_lossy(&output.stdout).trim().parse().ok())
+            .map(|seconds| UNIX_EPOCH + Duration::from_secs(seconds));
+
+        Self { operation, last_commit }
+    }
+}
+
+/// Most recent modification of anything under `path`.
+fn last_modified(path: &Path) -> Option<SystemTime> {
+    WalkDir::new(path)
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter_map(|entry| entry.metadata().ok())
+        .filter_map(|metadata| metadata.modified().ok())
+        .max()
+}
+
+/// Skips matches in repositories with unfinished work, per `GitConfig`.
+/// The repository a match lives in is the project it belongs to.
+pub struct GitGuard {
+    skip_in_progress: bool,
+    skip_newer_than_commit: bool,
+    repositories: Mutex<HashMap<PathBuf, Arc<RepositoryState>>>,
+}
+
+impl GitGuard {
+    /// `None` when no check is enabled.
+    pub fn new(config: &GitConfig) -> Option<Self> {
+        if !config.skip_in_progress && !config.skip_newer_than_commit {
+            return None;
+        }
+        Some(Self {
+            skip_in_progress: config.skip_in_progress,
+            skip_newer_than_commit: config.skip_newer_than_commit,
+            repositories: Mutex::new(HashMap::new()),
+        })
+    }
+
+    fn state(&self, root: &Path) -> Arc<RepositoryState> {
+        let mut repositories = self.repositories.lock().unwrap();
+        Arc::clone(
+            repositories
+                .entry(root.to_path_buf())
+                .or_insert_with(|| Arc::new(RepositoryState::load(root))),
+        )
+    }
+
+    /// Why the repository holding `path` should be left alone, if it should.
+    pub fn check(&self, path: &Path) -> Option<String> {
+        let root = repository_root(path)?;
+        let state = self.state(root);
+
+        if self.skip_in_progress {
+            if let Some(operation) = state.operation {
+                return Some(format!("{} in progress in {}", operation, root.display()));
+            }
+        }
+
+        if self.skip_newer_than_commit {
+            let last_commit = state.last_commit?;
+            let modified = last_modified(path)?;
+            // Commit times only have whole seconds
+            if let Ok(newer) = modified.duration_since(last_commit) {
+                if newer.as_secs() > 0 {
+                    return Some(format!("modified {} after the last commit", format_duration(newer)));
+                }
+            }
+        }
+
+        None
+    }
+}
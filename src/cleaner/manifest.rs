@@ -0,0 +1,121 @@
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use super::checksum::Digest;
+use super::store::EntryKind;
+
+/// Name of the manifest inside a backup directory of the `tree` format.
+pub const FILE_NAME: &str = ".backup-manifest.json";
+
+/// Suffix of the manifest next to an archive or dedup version.
+const EXTENSION: &str = "manifest.json";
+
+/// What one cleanup run put into its backup, so restoring never depends on
+/// the target patterns in effect at restore time.
+#[derive(Serialize, Deserialize, Default)]
+pub struct BackupManifest {
+    /// Project directory the items were cleaned from.
+    pub source_dir: PathBuf,
+    pub items: Vec<ManifestItem>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ManifestItem {
+    /// Absolute path the item was removed from, and is restored to.
+    pub original_path: PathBuf,
+    /// Where the item is inside its backup version.
+    pub backup_path: PathBuf,
+    pub kind: EntryKind,
+    pub size: u64,
+    /// Seconds since the Unix epoch.
+    pub mtime: i64,
+    /// Unix permission bits in octal, e.g. `755`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub permissions: Option<String>,
+    /// BLAKE3 hash of the item, see `checksum::item`.
+    pub checksum: String,
+}
+
+impl ManifestItem {
+    /// Describe the item at `original_path`, of which `digest` was just
+    /// taken, before it is moved away.
+    pub fn capture(original_path: &Path, backup_path: &Path, size: u64, digest: &Digest) -> io::Result<Self> {
+        let metadata = original_path.symlink_metadata()?;
+        let kind = if metadata.is_symlink() {
+            EntryKind::Symlink
+        } else if metadata.is_dir() {
+            EntryKind::Directory
+        } else {
+            EntryKind::File
+        };
+        let mtime = metadata
+            .modified()
+            .ok()
+            .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+            .map(|since_epoch| since_epoch.as_secs() as i64)
+            .unwrap_or_default();
+
+        #[cfg(unix)]
+        let permissions = {
+            use std::os::unix::fs::PermissionsExt;
+            Some(format!("{:o}", metadata.permissions().mode() & 0o7777))
+        };
+        #[cfg(not(unix))]
+        let permissions = None;
+
+        Ok(Self {
+            original_path: original_path.to_path_buf(),
+            backup_path: backup_path.to_path_buf(),
+            kind,
+            size,
+            mtime,
+            permissions,
+            checksum: digest.checksum.clone(),
+        })
+    }
+}
+
+/// Manifest of a `tree` backup directory.
+pub fn for_directory(dir: &Path) -> PathBuf {
+    dir.join(FILE_NAME)
+}
+
+/// Manifest next to an archive or dedup version: `<timestamp>.manifest.json`.
+pub fn for_file(version_file: &Path) -> PathBuf {
+    let file_name = version_file.file_name().unwrap_or_default().to_string_lossy();
+    let timestamp = file_name.split('.').next().unwrap_or_default();
+    version_file.with_file_name(format!("{}.{}", timestamp, EXTENSION))
+}
+
+/// Manifest of an existing backup version, directory or file.
+pub fn for_version(version_path: &Path) -> PathBuf {
+    if version_path.is_dir() {
+        for_directory(version_path)
+    } else {
+        for_file(version_path)
+    }
+}
+
+pub fn read(path: &Path) -> io::Result<BackupManifest> {
+    serde_json::from_reader(BufReader::new(File::open(path)?)).map_err(io::Error::from)
+}
+
+/// Add `items` to the manifest at `path`, replacing earlier entries for the
+/// same original paths (unversioned backups reuse one directory).
+pub fn record(path: &Path, source_dir: &Path, items: Vec<ManifestItem>) -> io::Result<()> {
+    let mut manifest = if path.exists() { read(path)? } else { BackupManifest::default() };
+    manifest.source_dir = source_dir.to_path_buf();
+    manifest
+        .items
+        .retain(|existing| !items.iter().any(|item| item.original_path == existing.original_path));
+    manifest.items.extend(items);
+
+    let partial = path.with_extension("partial");
+    let mut writer = BufWriter::new(File::create(&partial)?);
+    serde_json::to_writer_pretty(&mut writer, &manifest)?;
+    writer.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+    fs::rename(&partial, path)
+}
@@ -1,2 +1,15 @@
+mod age;
+pub mod archive;
+pub mod attributes;
+pub mod checksum;
+mod git;
+pub mod manifest;
+mod project;
 pub mod processor;
-mod types;
+pub mod retention;
+mod size;
+mod space;
+pub mod store;
+pub mod trash;
+pub mod types;
+pub mod versions;
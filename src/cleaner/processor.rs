@@ -1,19 +1,35 @@
 use colored::*;
-use inquire::Confirm;
+use humansize::{format_size, BINARY};
 use regex::Regex;
 use shellexpand;
+use std::borrow::Cow;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use tokio::fs::{self, create_dir_all};
 use walkdir::WalkDir;
 use futures::future::join_all;
-use std::sync::atomic::{AtomicUsize, Ordering};
 use tokio::task::JoinHandle;
 
+use super::age::AgePolicy;
+use super::archive;
+use super::attributes;
+use super::checksum::{self, Digest};
+use super::store;
+use super::git::{GitFilter, GitGuard};
+use super::manifest::{self, ManifestItem};
+use super::project::project_root;
+use super::size::SizePolicy;
+use super::space;
+use super::trash;
+use super::retention::RetentionPolicy;
+use super::versions::{self, BackupVersion};
 use super::types::*;
-use crate::Config;
+use crate::prompt::Interaction;
+use crate::config::units::parse_size;
+use crate::config::{BackupFormat, BackupMode, Config, LowSpaceAction};
+use crate::ecosystems;
 
-type CleanResult = Result<Vec<CleanTarget>, Box<dyn std::error::Error + Send>>;
+type CleanResult = Result<(Vec<CleanTarget>, Vec<SkippedTarget>), Box<dyn std::error::Error + Send>>;
 type CleanJoinHandle = JoinHandle<CleanResult>;
 
 // Add these macros at the top of the file
@@ -67,11 +83,61 @@ macro_rules! ensure_backup_subdir {
 
 pub struct Processor {
     config: Config,
+    dry_run: bool,
+    plan_output: Option<PathBuf>,
+    git_ignored_only: bool,
+    projects: Vec<String>,
+    project_order: ProjectOrder,
+    interaction: Interaction,
 }
 
 impl Processor {
     pub fn new(config: Config) -> Self {
-        Self { config }
+        Self {
+            config,
+            dry_run: false,
+            plan_output: None,
+            git_ignored_only: false,
+            projects: Vec::new(),
+            project_order: ProjectOrder::default(),
+            interaction: Interaction::default(),
+        }
+    }
+
+    pub fn interaction(mut self, interaction: Interaction) -> Self {
+        self.interaction = interaction;
+        self
+    }
+
+    /// Only compute and report the cleanup plan, never touch the filesystem.
+    pub fn dry_run(mut self, enabled: bool) -> Self {
+        self.dry_run = enabled;
+        self
+    }
+
+    /// Write the dry-run plan as JSON to this path.
+    pub fn plan_output(mut self, path: Option<PathBuf>) -> Self {
+        self.plan_output = path;
+        self
+    }
+
+    /// Only select paths git ignores, never anything tracked in the index,
+    /// whatever the target patterns say.
+    pub fn git_ignored_only(mut self, enabled: bool) -> Self {
+        self.git_ignored_only = enabled;
+        self
+    }
+
+    /// Only clean projects whose root matches one of these regexes;
+    /// every project when empty.
+    pub fn projects(mut self, patterns: Vec<String>) -> Self {
+        self.projects = patterns;
+        self
+    }
+
+    pub fn project_order(mut self, order: ProjectOrder) -> Self {
+        self.project_order = order;
+        self
     }
 
     pub async fn process(&self) -> Result<(), Box<dyn std::error::Error>> {
@@ -82,30 +148,70 @@ impl Processor {
             return Ok(());
         }
 
-        let should_continue = Confirm::new("🤔 Do you want to proceed with the cleanup?")
-            .with_default(false)
-            .prompt()?;
+        if self.dry_run {
+            // In the summary's order, so the plan reads alongside it
+            let items: Vec<CleanTarget> = summary.ordered_items().into_iter().cloned().collect();
+            let plan = self.plan_cleanup(&items)?;
+            plan.display_plan();
+            if let Some(problem) = self.space_problem(&plan, 0)? {
+                let outcome = match self.config.backup.on_low_space {
+                    LowSpaceAction::Abort => "the run would stop before changing anything",
+                    LowSpaceAction::Prune => "the oldest backup versions would be removed first",
+                    LowSpaceAction::Delete => "the items would be deleted without a backup",
+                };
+                println!("{}", format!("⚠️  The backup would not fit: {}; {}.", problem, outcome).yellow());
+            }
+
+            if let Some(path) = &self.plan_output {
+                let json = serde_json::to_string_pretty(&plan)?;
+                fs::write(path, json).await
+                    .map_err(|e| format!("Failed to write plan to {}: {}", path.display(), e))?;
+                println!("{}", format!("📝 Plan written to {}", path.display()).cyan());
+            }
+            return Ok(());
+        }
 
-        if !should_continue {
+        let selected = self.pick_items(&summary)?;
+        // The checklist starts fully ticked, so a stray Enter must not be enough
+        if selected.is_empty()
+            || !self
+                .interaction
+                .confirm("🤔 Do you want to proceed with the cleanup?", None)?
+        {
             println!("{}", "🚫 Cleanup cancelled.".yellow());
             return Ok(());
         }
 
-        self.execute_cleanup(&summary).await?;
+        let plan = self.plan_cleanup(&selected)?;
+        self.execute_cleanup(&plan).await?;
         Ok(())
     }
 
     async fn scan_directory(&self) -> Result<CleanSummary, Box<dyn std::error::Error>> {
         let source_dir = PathBuf::from(&self.config.dir);
         // let backup_dir = PathBuf::from(&self.config.backup.dir);
-        let total_items = Arc::new(AtomicUsize::new(0));
-        let total_size = Arc::new(AtomicUsize::new(0));
+        let project_patterns = self
+            .projects
+            .iter()
+            .map(|pattern| Regex::new(pattern).map_err(|e| format!("Invalid --project pattern \"{}\": {}", pattern, e)))
+            .collect::<Result<Vec<Regex>, String>>()?;
 
         // Compile patterns once
         let file_patterns = Arc::new(create_regex!(&self.config.target.files_patterns));
         let dir_patterns = Arc::new(create_regex!(&self.config.target.dirs_patterns));
         let exception_files = Arc::new(create_regex!(&self.config.exclude.files_patterns));
         let exception_dirs = Arc::new(create_regex!(&self.config.exclude.dirs_patterns));
+        let marker_rules: Arc<Vec<(Regex, Vec<String>)>> = Arc::new(
+            self.config
+                .marker_rules
+                .iter()
+                .map(|rule| (Regex::new(&rule.pattern).unwrap(), rule.markers.clone()))
+                .collect(),
+        );
+        let age_policy = Arc::new(AgePolicy::new(&self.config.age));
+        let size_policy = Arc::new(SizePolicy::new(&self.config.size));
+        let git_filter = self.git_ignored_only.then(|| Arc::new(GitFilter::default()));
+        let git_guard = GitGuard::new(&self.config.git).map(Arc::new);
 
         let mut root_entries = Vec::new();
         let mut dir_reader = tokio::fs::read_dir(&source_dir).await?;
@@ -123,13 +229,17 @@ impl Processor {
             let dir_patterns = Arc::clone(&dir_patterns);
             let exception_files = Arc::clone(&exception_files);
             let exception_dirs = Arc::clone(&exception_dirs);
-            let total_items = Arc::clone(&total_items);
-            let total_size = Arc::clone(&total_size);
+            let marker_rules = Arc::clone(&marker_rules);
+            let age_policy = Arc::clone(&age_policy);
+            let size_policy = Arc::clone(&size_policy);
+            let git_filter = git_filter.clone();
+            let git_guard = git_guard.clone();
             
             let chunk_paths: Vec<PathBuf> = chunk.iter().map(|entry| entry.path()).collect();
 
             let handle = tokio::spawn(async move {
                 let mut targets = Vec::new();
+                let mut skipped = Vec::new();
                 let mut parent_dirs_to_clean = Vec::new();
 
                 for path in chunk_paths {
@@ -139,7 +249,8 @@ impl Processor {
                             let entry = entry.map_err(|e| Box::new(e) as Box<dyn std::error::Error + Send>)?;
                             let path = entry.path();
                             
-                            // Skip if any parent directory is already marked for cleanup
+                            // Skip if any parent directory is already marked for cleanup,
+                            // or was skipped as a whole because it is still in use
                             if parent_dirs_to_clean.iter().any(|parent: &PathBuf| path.starts_with(parent)) {
                                 continue;
                             }
@@ -151,6 +262,44 @@ impl Processor {
                             if !Self::is_exception(relative_path, is_dir, &exception_files, &exception_dirs)
                                 && Self::should_clean(relative_path, is_dir, &file_patterns, &dir_patterns)
                             {
+                                if let Some(reason) = git_filter.as_ref().and_then(|filter| filter.check(path)) {
+                                    skipped.push(SkippedTarget {
+                                        source_path: path.to_path_buf(),
+                                        reason,
+                                    });
+                                    continue;
+                                }
+
+                                if let Some(markers) = Self::missing_marker(path, relative_path, &marker_rules) {
+                                    skipped.push(SkippedTarget {
+                                        source_path: path.to_path_buf(),
+                                        reason: format!("no {} next to it", markers.join(" / ")),
+                                    });
+                                    continue;
+                                }
+
+                                if let Some(reason) = git_guard.as_ref().and_then(|guard| guard.check(path)) {
+                                    skipped.push(SkippedTarget {
+                                        source_path: path.to_path_buf(),
+                                        reason,
+                                    });
+                                    if is_dir {
+                                        parent_dirs_to_clean.push(path.to_path_buf());
+                                    }
+                                    continue;
+                                }
+
+                                if let Some(reason) = age_policy.check(path, relative_path) {
+                                    skipped.push(SkippedTarget {
+                                        source_path: path.to_path_buf(),
+                                        reason,
+                                    });
+                                    if is_dir {
+                                        parent_dirs_to_clean.push(path.to_path_buf());
+                                    }
+                                    continue;
+                                }
+
                                 let size = if is_dir {
                                     fs_extra::dir::get_size(path).unwrap_or(0)
                                 } else {
@@ -159,13 +308,23 @@ impl Processor {
                                         .len()
                                 };
 
-                                total_size.fetch_add(size as usize, Ordering::Relaxed);
-                                total_items.fetch_add(1, Ordering::Relaxed);
+                                if let Some(reason) = size_policy.check(size, relative_path) {
+                                    skipped.push(SkippedTarget {
+                                        source_path: path.to_path_buf(),
+                                        reason,
+                                    });
+                                    if is_dir {
+                                        parent_dirs_to_clean.push(path.to_path_buf());
+                                    }
+                                    continue;
+                                }
 
                                 let target = CleanTarget {
                                     source_path: path.to_path_buf(),
+                                    project: project_root(path, &source_dir),
                                     // backup_path: backup_dir.join(relative_path),
                                     is_directory: is_dir,
+                                    size,
                                 };
 
                                 if is_dir {
@@ -178,7 +337,7 @@ impl Processor {
                     }
                 }
 
-                Ok(targets)
+                Ok((targets, skipped))
             });
 
             handles.push(handle);
@@ -187,68 +346,355 @@ impl Processor {
         // Collect results
         let results = join_all(handles).await;
         let mut all_targets = Vec::new();
-        
+        let mut all_skipped = Vec::new();
+
         for result in results {
             match result? {
-                Ok(targets) => all_targets.extend(targets),
+                Ok((targets, skipped)) => {
+                    all_targets.extend(targets);
+                    all_skipped.extend(skipped);
+                }
                 Err(e) => eprintln!("{}", format!("Error scanning directory: {}", e).red()),
             }
         }
 
+        let mut unselected_projects = Vec::new();
+        if !project_patterns.is_empty() {
+            all_targets.retain(|target: &CleanTarget| {
+                let project = target.project.to_string_lossy();
+                let selected = project_patterns.iter().any(|pattern| pattern.is_match(&project));
+                if !selected && !unselected_projects.contains(&target.project) {
+                    unselected_projects.push(target.project.clone());
+                }
+                selected
+            });
+        }
+
+        // Apply the run budget across all chunks at once
+        let (all_targets, over_budget) = size_policy.select(all_targets, &age_policy);
+        all_skipped.extend(over_budget);
+
         Ok(CleanSummary {
-            total_items: total_items.load(Ordering::Relaxed),
-            total_size: total_size.load(Ordering::Relaxed) as u64,
+            total_items: all_targets.len(),
+            total_size: all_targets.iter().map(|target| target.size).sum(),
             source_total_size: fs_extra::dir::get_size(&source_dir).unwrap_or(0),
             items: all_targets,
+            skipped: all_skipped,
+            project_order: self.project_order,
+            unselected_projects: unselected_projects.len(),
         })
     }
 
-    async fn execute_cleanup(&self, summary: &CleanSummary) -> Result<(), Box<dyn std::error::Error>> {
-        let valid_items: Vec<_> = summary
-            .items
+    /// Ask which of the found items to clean, grouped by project with
+    /// everything ticked; the answer replaces an all-or-nothing confirmation.
+    fn pick_items(&self, summary: &CleanSummary) -> Result<Vec<CleanTarget>, Box<dyn std::error::Error>> {
+        let source_dir = PathBuf::from(&self.config.dir);
+        let items = summary.ordered_items();
+
+        let options = items
             .iter()
-            .filter(|target| safe_path_exists!(&target.source_path))
+            .map(|item| {
+                let project = item.project.strip_prefix(&source_dir).unwrap_or(&item.project);
+                let project = if project.as_os_str().is_empty() { Path::new(".") } else { project };
+                let path = item.source_path.strip_prefix(&item.project).unwrap_or(&item.source_path);
+                format!(
+                    "{} › {}{} ({})",
+                    project.display(),
+                    path.display(),
+                    if item.is_directory { "/" } else { "" },
+                    format_size(item.size, BINARY)
+                )
+            })
             .collect();
 
-        println!("🔍 Found {} items to process", valid_items.len());
+        let indices = self
+            .interaction
+            .multi_select("🤔 Which items do you want to clean?", options)?;
+        let selected: Vec<CleanTarget> = indices.into_iter().map(|index| items[index].clone()).collect();
 
-        let mut success_count = 0;
-        let mut error_count = 0;
+        if !selected.is_empty() {
+            println!(
+                "{}",
+                format!(
+                    "✅ {} of {} item(s) selected ({})",
+                    selected.len(),
+                    items.len(),
+                    format_size(selected.iter().map(|item| item.size).sum::<u64>(), BINARY)
+                )
+                .green()
+            );
+        }
+        Ok(selected)
+    }
+
+    /// Decide where every item goes without modifying anything on disk.
+    fn plan_cleanup(&self, items: &[CleanTarget]) -> Result<CleanPlan, Box<dyn std::error::Error>> {
+        let valid_items: Vec<_> = items
+            .iter()
+            .filter(|target| safe_path_exists!(&target.source_path))
+            .collect();
+        let total_size = valid_items.iter().map(|target| target.size).sum();
 
         if !self.config.backup.enabled {
-            for target in &valid_items {
-                handle_path_operation!(
-                    if target.is_directory {
-                        fs::remove_dir_all(&target.source_path).await
-                    } else {
-                        fs::remove_file(&target.source_path).await
-                    },
-                    &target.source_path,
-                    "Failed to remove item"
+            return Ok(CleanPlan {
+                backup_dir: None,
+                create_backup_dir: false,
+                version_file: None,
+                manifest: None,
+                total_size,
+                operations: valid_items
+                    .into_iter()
+                    .map(|target| CleanOperation {
+                        source_path: target.source_path.clone(),
+                        project: target.project.clone(),
+                        is_directory: target.is_directory,
+                        size: target.size,
+                        backup_path: None,
+                        trash_info: None,
+                        remove_existing_backup: false,
+                    })
+                    .collect(),
+            });
+        }
+
+        if self.config.backup.mode == BackupMode::Trash {
+            let trash_dir = trash::home_trash()
+                .ok_or("Cannot determine the trash directory, set XDG_DATA_HOME or HOME")?;
+            let mut taken = std::collections::HashSet::new();
+
+            let operations = valid_items
+                .into_iter()
+                .map(|target| {
+                    // Items on other filesystems go to the trash there, without copying
+                    let trash_dir = trash::trash_for(&target.source_path, &trash_dir);
+                    let name = trash::unique_name(&trash_dir, &target.source_path, &mut taken);
+                    CleanOperation {
+                        source_path: target.source_path.clone(),
+                        project: target.project.clone(),
+                        is_directory: target.is_directory,
+                        size: target.size,
+                        backup_path: Some(trash_dir.join("files").join(&name)),
+                        trash_info: Some(trash::info_path(&trash_dir, &name)),
+                        remove_existing_backup: false,
+                    }
+                })
+                .collect();
+
+            return Ok(CleanPlan {
+                create_backup_dir: !safe_path_exists!(&trash_dir.join("files")),
+                backup_dir: Some(trash_dir),
+                version_file: None,
+                manifest: None,
+                total_size,
+                operations,
+            });
+        }
+
+        let main_backup_dir =
+            PathBuf::from(shellexpand::tilde(&self.config.backup.dir).into_owned());
+
+        // Create timestamp directory directly after main backup directory if versioning is enabled
+        let timestamp = versions::unused_name(
+            &main_backup_dir,
+            &chrono::Local::now().format(versions::TIMESTAMP_FORMAT).to_string(),
+        );
+        let version_file = match self.config.backup.format {
+            BackupFormat::Tree => None,
+            BackupFormat::TarZst => Some(main_backup_dir.join(format!("{}.{}", timestamp, archive::EXTENSION))),
+            BackupFormat::Dedup => Some(main_backup_dir.join(format!("{}.{}", timestamp, store::MANIFEST_EXTENSION))),
+        };
+        let versioned_backup_dir = if let Some(version_file) = &version_file {
+            // Items are shown at their place inside the archive or manifest
+            version_file.clone()
+        } else if self.config.backup.versioning {
+            main_backup_dir.join(&timestamp)
+        } else {
+            main_backup_dir.clone()
+        };
+
+        let mut operations = Vec::with_capacity(valid_items.len());
+        for target in valid_items {
+            // Create the final backup path by joining the versioned backup directory with the item's name
+            let final_backup_path = versioned_backup_dir.join(self.backup_name(&target.source_path)?);
+
+            operations.push(CleanOperation {
+                source_path: target.source_path.clone(),
+                project: target.project.clone(),
+                is_directory: target.is_directory,
+                size: target.size,
+                remove_existing_backup: version_file.is_none()
+                    && safe_path_exists!(&final_backup_path)
+                    && !self.config.backup.versioning,
+                backup_path: Some(final_backup_path),
+                trash_info: None,
+            });
+        }
+
+        Ok(CleanPlan {
+            create_backup_dir: !safe_path_exists!(&main_backup_dir),
+            backup_dir: Some(main_backup_dir),
+            manifest: Some(match &version_file {
+                Some(version_file) => manifest::for_file(version_file),
+                None => manifest::for_directory(&versioned_backup_dir),
+            }),
+            version_file,
+            total_size,
+            operations,
+        })
+    }
+
+    /// Why the backup of `plan` would not fit on its filesystem or within
+    /// `backup.max_size`, if it would not, once `freed` more bytes of
+    /// backups are gone.
+    fn space_problem(&self, plan: &CleanPlan, freed: u64) -> std::io::Result<Option<String>> {
+        let backup_dir = match &plan.backup_dir {
+            Some(dir) if self.config.backup.mode == BackupMode::Directory => dir,
+            _ => return Ok(None),
+        };
+
+        // Tree backups rename items on the same filesystem, which takes no space
+        let copied: u64 = plan
+            .operations
+            .iter()
+            .filter(|target| {
+                // The same check `transfer` makes to choose between renaming and copying
+                let dir = target.backup_path.as_deref().and_then(Path::parent).unwrap_or(backup_dir);
+                plan.version_file.is_some() || !space::same_filesystem(&target.source_path, dir)
+            })
+            .map(|target| target.size)
+            .sum();
+        let available = space::available(backup_dir)?.saturating_add(freed);
+        if copied > available {
+            return Ok(Some(format!(
+                "{} to copy but only {} free for {}",
+                format_size(copied, BINARY),
+                format_size(available, BINARY),
+                backup_dir.display()
+            )));
+        }
+
+        if let Some(max_size) = self.config.backup.max_size.as_deref() {
+            let max_size = parse_size(max_size).unwrap();
+            let used = if backup_dir.exists() {
+                fs_extra::dir::get_size(backup_dir).map_err(|e| std::io::Error::other(e.to_string()))?
+            } else {
+                0
+            }
+            .saturating_sub(freed);
+            if used + plan.total_size > max_size {
+                return Ok(Some(format!(
+                    "backups would grow to {}, over backup.max_size ({})",
+                    format_size(used + plan.total_size, BINARY),
+                    format_size(max_size, BINARY)
+                )));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Apply `backup.on_low_space` when the backup of `plan` would not fit;
+    /// returns the plan to carry out.
+    async fn ensure_space<'a>(&self, plan: &'a CleanPlan) -> Result<Cow<'a, CleanPlan>, Box<dyn std::error::Error>> {
+        let problem = match self.space_problem(plan, 0)? {
+            Some(problem) => problem,
+            None => return Ok(Cow::Borrowed(plan)),
+        };
+
+        match self.config.backup.on_low_space {
+            LowSpaceAction::Abort => Err(format!("Not enough room for the backup: {}. Nothing was changed.", problem).into()),
+            LowSpaceAction::Delete => {
+                println!(
+                    "{}",
+                    format!("⚠️  Low on backup space: {}, deleting the items without a backup (backup.on_low_space).", problem).yellow()
                 );
+                Ok(Cow::Owned(plan.without_backup()))
+            }
+            LowSpaceAction::Prune => {
+                let backup_dir = plan.backup_dir.as_deref().expect("only backups can run out of space");
+                let mut versions = versions::list(backup_dir).await?;
+
+                // Give up before removing anything if even keeping only the newest version is not enough
+                let usages = versions
+                    .iter()
+                    .map(BackupVersion::usage)
+                    .collect::<std::io::Result<Vec<_>>>()
+                    .map_err(|e| format!("Failed to measure backups in {}: {}", backup_dir.display(), e))?;
+                let freeable = RetentionPolicy::total_size(&usages, |_| true)
+                    - RetentionPolicy::total_size(&usages, |index| index == 0);
+                if let Some(problem) = self.space_problem(plan, freeable)? {
+                    return Err(format!(
+                        "Not enough room for the backup even after pruning: {}. Nothing was changed.",
+                        problem
+                    )
+                    .into());
+                }
+
                 println!(
                     "{}",
-                    format!(
-                        "🗑️  Removed {}: {}",
+                    format!("⚠️  Low on backup space: {}, removing the oldest backup versions (backup.on_low_space).", problem).yellow()
+                );
+                while let Some(problem) = self.space_problem(plan, 0)? {
+                    // The newest version always stays
+                    if versions.len() <= 1 {
+                        return Err(format!("Not enough room for the backup even after pruning: {}.", problem).into());
+                    }
+                    let oldest = versions.pop().expect("checked above");
+                    oldest
+                        .remove()
+                        .await
+                        .map_err(|e| format!("Failed to remove {}: {}", oldest.path.display(), e))?;
+                    versions::collect_garbage(backup_dir).await?;
+                    println!("  {} Removed backup version {}", "🗑️".bold(), oldest.name.bright_white());
+                }
+                Ok(Cow::Borrowed(plan))
+            }
+        }
+    }
+
+    async fn execute_cleanup(&self, plan: &CleanPlan) -> Result<(), Box<dyn std::error::Error>> {
+        // Before the first item moves, so a full disk never leaves a run half done
+        let plan = self.ensure_space(plan).await?;
+        let plan = plan.as_ref();
+        println!("🔍 Found {} items to process", plan.operations.len());
+
+        let mut success_count = 0;
+        let mut error_count = 0;
+
+        let main_backup_dir = match &plan.backup_dir {
+            Some(dir) => dir,
+            None => {
+                for target in &plan.operations {
+                    handle_path_operation!(
                         if target.is_directory {
-                            "directory"
+                            fs::remove_dir_all(&target.source_path).await
                         } else {
-                            "file"
+                            fs::remove_file(&target.source_path).await
                         },
-                        target.source_path.display()
-                    )
-                    .green()
-                );
+                        &target.source_path,
+                        "Failed to remove item"
+                    );
+                    println!(
+                        "{}",
+                        format!(
+                            "🗑️  Removed {}: {}",
+                            if target.is_directory {
+                                "directory"
+                            } else {
+                                "file"
+                            },
+                            target.source_path.display()
+                        )
+                        .green()
+                    );
+                }
+                return Ok(());
             }
-            return Ok(());
-        }
+        };
 
-        let main_backup_dir =
-            PathBuf::from(shellexpand::tilde(&self.config.backup.dir).into_owned());
-        if !safe_path_exists!(&main_backup_dir) {
+        if plan.create_backup_dir && !safe_path_exists!(main_backup_dir) {
             handle_path_operation!(
-                create_dir_all(&main_backup_dir).await,
-                &main_backup_dir,
+                create_dir_all(main_backup_dir).await,
+                main_backup_dir,
                 "Failed to create main backup directory"
             );
             println!(
@@ -257,32 +703,35 @@ impl Processor {
             );
         }
 
-        // Create timestamp directory directly after main backup directory if versioning is enabled
-        let versioned_backup_dir = if self.config.backup.versioning {
-            let timestamp = chrono::Local::now().format("%Y%m%d_%H%M%S").to_string();
-            main_backup_dir.join(&timestamp)
-        } else {
-            main_backup_dir.clone()
-        };
+        if let Some(version_file) = &plan.version_file {
+            return self.write_version_file(plan, main_backup_dir, version_file).await;
+        }
 
-        for target in &valid_items {
+        let mut recorded = Vec::new();
+        for target in &plan.operations {
             // Clear previous line and show processing status
             println!("\n{}", "━".repeat(80).bright_black());
             println!("{} Processing: {}", "⏳".bold(), target.source_path.display().to_string().bright_white());
 
-            // Calculate the relative path from the source directory to the target
-            let relative_path = target.source_path.strip_prefix(PathBuf::from(&self.config.dir))
-                .map_err(|e| format!("Failed to calculate relative path: {}", e))?;
-
-            // Create the final backup path by joining the versioned backup directory with the relative path
-            let final_backup_path = versioned_backup_dir.join(relative_path);
+            let final_backup_path = match &target.backup_path {
+                Some(path) => path,
+                None => continue,
+            };
 
             println!("  {} Source:      {}", "→".bright_blue(), target.source_path.display().to_string().bright_white());
             println!("  {} Destination: {}", "→".bright_blue(), final_backup_path.display().to_string().bright_white());
 
-            ensure_backup_subdir!(&final_backup_path);
+            // Before anything else creates them, so they get the modes the spec asks for
+            if let Some(trash_dir) = target.trash_info.as_deref().and_then(Path::parent).and_then(Path::parent) {
+                if let Err(e) = trash::create(trash_dir) {
+                    println!("  {} {}", "❌".bold(), format!("Failed to create {}: {}", trash_dir.display(), e).red());
+                    error_count += 1;
+                    continue;
+                }
+            }
+            ensure_backup_subdir!(final_backup_path);
 
-            if safe_path_exists!(&final_backup_path) && !self.config.backup.versioning {
+            if target.remove_existing_backup && safe_path_exists!(final_backup_path) {
                 print!(
                     "🗑️ Removing existing backup: {}",
                     final_backup_path.display()
@@ -293,68 +742,49 @@ impl Processor {
                     } else {
                         fs::remove_file(&final_backup_path).await
                     },
-                    &final_backup_path,
+                    final_backup_path,
                     "Failed to remove existing backup"
                 );
             }
 
-            println!("📦 Copying to backup location ...");
-
-            // Backup and remove
-            if target.is_directory {
-                println!("  {} {}", "📦".bold(), "Creating backup structure...".bright_cyan());
-                match fs::create_dir_all(&final_backup_path).await {
-                    Ok(_) => {
-                        println!("  {} {}", "📦".bold(), "Copying contents...".bright_cyan());
-                        let options = fs_extra::dir::CopyOptions::new()
-                            .overwrite(true)
-                            .content_only(false);
-
-                        match fs_extra::dir::copy(&target.source_path, final_backup_path.parent().unwrap(), &options) {
-                            Ok(_) => {
-                                match fs::remove_dir_all(&target.source_path).await {
-                                    Ok(_) => {
-                                        println!("  {} {}", "✅".bold(), "Operation completed".green());
-                                        success_count += 1;
-                                    },
-                                    Err(e) => {
-                                        println!("  {} {}", "❌".bold(), format!("Removal failed: {}", e).red());
-                                        error_count += 1;
-                                    }
-                                }
-                            }
-                            Err(e) => {
-                                println!("\r{}", format!("⚠️  Failed to copy directory {}: {}", 
-                                    target.source_path.display(), e).red());
-                                error_count += 1;
-                                continue;
-                            }
-                        }
-                    },
-                    Err(e) => {
-                        println!("  {} {}", "❌".bold(), format!("Failed to create backup directory: {}", e).red());
-                        error_count += 1;
-                        continue;
-                    }
+            // Describe the item for the manifest and verification while it is still in place
+            let verify = self.config.backup.verify;
+            let captured = match (plan.manifest.is_some() || verify).then(|| self.capture(target)).transpose() {
+                Ok(captured) => captured,
+                Err(e) => {
+                    println!("  {} {}", "❌".bold(), format!("Failed to read {}: {}", target.source_path.display(), e).red());
+                    error_count += 1;
+                    continue;
                 }
-            } else {
-                match fs::copy(&target.source_path, &final_backup_path).await {
-                    Ok(_) => {
-                        match fs::remove_file(&target.source_path).await {
-                            Ok(_) => {
-                                println!("  {} {}", "✅".bold(), "Operation completed".green());
-                                success_count += 1;
-                            },
-                            Err(e) => {
-                                println!("  {} {}", "❌".bold(), format!("Removal failed: {}", e).red());
-                                error_count += 1;
-                            }
+            };
+
+            // Record the original location first, as the spec asks, so the
+            // trashed item is never left without its info file
+            if let Some(info_path) = &target.trash_info {
+                if let Err(e) = Self::write_trash_info(info_path, &target.source_path).await {
+                    println!("  {} {}", "❌".bold(), format!("Failed to write {}: {}", info_path.display(), e).red());
+                    error_count += 1;
+                    continue;
+                }
+            }
+
+            let (manifest_item, digest) = captured.unzip();
+            let expected = digest.as_ref().filter(|_| verify);
+            match Self::transfer(&target.source_path, final_backup_path, target.is_directory, expected).await {
+                Ok(()) => {
+                    println!("  {} {}", "✅".bold(), "Operation completed".green());
+                    recorded.extend(manifest_item.filter(|_| plan.manifest.is_some()));
+                    success_count += 1;
+                }
+                Err(e) => {
+                    println!("  {} {}", "❌".bold(), e.red());
+                    error_count += 1;
+                    if let Some(info_path) = &target.trash_info {
+                        if !safe_path_exists!(final_backup_path) {
+                            let _ = fs::remove_file(info_path).await;
                         }
-                    },
-                    Err(e) => {
-                        println!("  {} {}", "❌".bold(), format!("Backup failed: {}", e).red());
-                        error_count += 1;
                     }
+                    continue;
                 }
             }
 
@@ -372,14 +802,204 @@ impl Processor {
             println!("{}", "━".repeat(80).bright_black());
         }
 
-        // Add summary at the end
+        if let Some(manifest_path) = &plan.manifest {
+            if !recorded.is_empty() {
+                manifest::record(manifest_path, Path::new(&self.config.dir), recorded)
+                    .map_err(|e| format!("Failed to write manifest {}: {}", manifest_path.display(), e))?;
+            }
+        }
+
+        Self::display_results(success_count, error_count);
+        Ok(())
+    }
+
+    /// Write every item into the run's archive or dedup store, and only remove
+    /// the originals once the whole version is safely on disk.
+    async fn write_version_file(
+        &self,
+        plan: &CleanPlan,
+        backup_dir: &Path,
+        version_file: &Path,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let source_dir = PathBuf::from(&self.config.dir);
+        let items = plan
+            .operations
+            .iter()
+            .map(|target| Ok((target.source_path.clone(), self.backup_name(&target.source_path)?)))
+            .collect::<Result<Vec<_>, String>>()?;
+
+        // Describe the items before anything moves; nothing is removed if this fails
+        let (recorded, digests): (Vec<ManifestItem>, Vec<Digest>) = plan
+            .operations
+            .iter()
+            .map(|target| self.capture(target))
+            .collect::<std::io::Result<Vec<_>>>()
+            .map_err(|e| format!("Failed to read the items to back up: {}", e))?
+            .into_iter()
+            .unzip();
+
+        println!(
+            "📦 Storing {} item(s) in {} ...",
+            items.len(),
+            version_file.display().to_string().bright_white()
+        );
+        let names: Vec<PathBuf> = items.iter().map(|(_, name)| name.clone()).collect();
+        let format = self.config.backup.format;
+        let (store_dir, path) = (backup_dir.to_path_buf(), version_file.to_path_buf());
+        let written = tokio::task::spawn_blocking(move || match format {
+            BackupFormat::Dedup => store::write(&store_dir, &path, &items),
+            _ => archive::write(&path, &items),
+        });
+        if let Err(e) = written.await? {
+            // Someone else's version, which must stay
+            if e.kind() != std::io::ErrorKind::AlreadyExists {
+                let _ = fs::remove_file(version_file).await;
+            }
+            return Err(format!("Failed to write {}: {}", version_file.display(), e).into());
+        }
+        if self.config.backup.verify {
+            println!("🔎 Verifying {} ...", version_file.display().to_string().bright_white());
+            let path = version_file.to_path_buf();
+            let stored_names = names.clone();
+            let stored = tokio::task::spawn_blocking(move || versions::digests(&path, &stored_names)).await?;
+            let problem = match stored {
+                Ok(stored) => names
+                    .iter()
+                    .zip(&digests)
+                    .find_map(|(name, digest)| Self::mismatch(digest, stored.get(name)).map(|problem| (name, problem)))
+                    .map(|(name, problem)| format!("{}: {}", name.display(), problem)),
+                Err(e) => Some(e.to_string()),
+            };
+            if let Some(problem) = problem {
+                let _ = fs::remove_file(version_file).await;
+                return Err(format!("Verification of {} failed ({}), nothing was removed", version_file.display(), problem).into());
+            }
+        }
+        if let Some(manifest_path) = &plan.manifest {
+            if let Err(e) = manifest::record(manifest_path, &source_dir, recorded) {
+                let _ = fs::remove_file(version_file).await;
+                return Err(format!("Failed to write manifest {}: {}", manifest_path.display(), e).into());
+            }
+        }
+
+        let mut success_count = 0;
+        let mut error_count = 0;
+        for target in &plan.operations {
+            let removed = if target.is_directory {
+                fs::remove_dir_all(&target.source_path).await
+            } else {
+                fs::remove_file(&target.source_path).await
+            };
+            match removed {
+                Ok(()) => {
+                    println!("{}", format!("🗑️  Removed {}", target.source_path.display()).green());
+                    success_count += 1;
+                }
+                Err(e) => {
+                    println!("  {} {}", "❌".bold(), format!("Removal of {} failed: {}", target.source_path.display(), e).red());
+                    error_count += 1;
+                }
+            }
+        }
+
+        Self::display_results(success_count, error_count);
+        Ok(())
+    }
+
+    /// Manifest entry for an item about to be backed up, and the digest it
+    /// records, which the backup is verified against.
+    fn capture(&self, target: &CleanOperation) -> std::io::Result<(ManifestItem, Digest)> {
+        let backup_path = self.backup_name(&target.source_path).map_err(std::io::Error::other)?;
+        let digest = checksum::item(&target.source_path)?;
+        let item = ManifestItem::capture(&target.source_path, &backup_path, target.size, &digest)?;
+        Ok((item, digest))
+    }
+
+    /// Where `source_path` goes inside a backup version: its path below
+    /// `dir`, in the namespace of `dir`.
+    fn backup_name(&self, source_path: &Path) -> Result<PathBuf, String> {
+        let source_dir = Path::new(&self.config.dir);
+        let relative_path = source_path
+            .strip_prefix(source_dir)
+            .map_err(|e| format!("Failed to calculate relative path: {}", e))?;
+        Ok(versions::namespace(source_dir).join(relative_path))
+    }
+
+    fn display_results(success_count: usize, error_count: usize) {
         println!("\n{}", "━".repeat(80).bright_black());
         println!("📊 Cleanup Summary:");
         println!("  {} Successful: {}", "→".bright_blue(), success_count.to_string().green());
         println!("  {} Failed: {}", "→".bright_blue(), error_count.to_string().red());
         println!("{}", "━".repeat(80).bright_black());
+    }
 
-        Ok(())
+    /// How a backup with digest `stored` differs from an original with
+    /// digest `expected`, if it does.
+    fn mismatch(expected: &Digest, stored: Option<&Digest>) -> Option<String> {
+        match stored {
+            None => Some("missing from the backup".to_string()),
+            Some(stored) if stored.size != expected.size => Some(format!(
+                "{} backed up instead of {}",
+                format_size(stored.size, BINARY),
+                format_size(expected.size, BINARY)
+            )),
+            Some(stored) if stored.checksum != expected.checksum => Some("checksum mismatch".to_string()),
+            Some(_) => None,
+        }
+    }
+
+    /// Move `source` to `destination`: a rename on the same device, otherwise
+    /// a copy, checked against `expected` when given, and a removal.
+    async fn transfer(source: &Path, destination: &Path, is_directory: bool, expected: Option<&Digest>) -> Result<(), String> {
+        if destination.parent().is_some_and(|parent| space::same_filesystem(source, parent)) {
+            println!("🚚 Moving to backup location ...");
+            match fs::rename(source, destination).await {
+                Ok(()) => return Ok(()),
+                Err(e) => println!(
+                    "  {} {}",
+                    "⚠️".bold(),
+                    format!("Rename failed ({}), copying instead", e).yellow()
+                ),
+            }
+        }
+
+        println!("📦 Copying to backup location ...");
+        // Keeps permissions, times, symlinks, hard links and extended attributes
+        let (from, to) = (source.to_path_buf(), destination.to_path_buf());
+        tokio::task::spawn_blocking(move || attributes::copy(&from, &to))
+            .await
+            .map_err(|e| e.to_string())?
+            .map_err(|e| format!("Failed to copy {}: {}", source.display(), e))?;
+
+        if let Some(expected) = expected {
+            println!("  {} {}", "🔎".bold(), "Verifying backup...".bright_cyan());
+            let stored = checksum::item(destination).ok();
+            if let Some(problem) = Self::mismatch(expected, stored.as_ref()) {
+                // Keep the original and drop the bad copy
+                let _ = if is_directory {
+                    fs::remove_dir_all(destination).await
+                } else {
+                    fs::remove_file(destination).await
+                };
+                return Err(format!("Verification failed ({}), original kept", problem));
+            }
+        }
+
+        if is_directory {
+            fs::remove_dir_all(source).await
+        } else {
+            fs::remove_file(source).await
+        }
+        .map_err(|e| format!("Removal failed: {}", e))
+    }
+
+    async fn write_trash_info(info_path: &Path, original: &Path) -> std::io::Result<()> {
+        if let Some(parent) = info_path.parent() {
+            create_dir_all(parent).await?;
+        }
+        // `create_new` claims the name atomically against other trashing programs
+        let mut file = fs::OpenOptions::new().write(true).create_new(true).open(info_path).await?;
+        tokio::io::AsyncWriteExt::write_all(&mut file, trash::info_contents(original).as_bytes()).await
     }
 
     #[inline]
@@ -402,6 +1022,46 @@ impl Processor {
         !is_dir && exception_files.iter().any(|re| re.is_match(&path_str))
     }
 
+    /// If `relative_path` falls under marker rules and none of their markers
+    /// exist next to `path`, return the markers that were looked for.
+    fn missing_marker(path: &Path, relative_path: &Path, marker_rules: &[(Regex, Vec<String>)]) -> Option<Vec<String>> {
+        let path_str = relative_path.to_string_lossy();
+        let mut required: Vec<String> = Vec::new();
+        for (pattern, markers) in marker_rules {
+            if pattern.is_match(&path_str) {
+                for marker in markers {
+                    if !required.contains(marker) {
+                        required.push(marker.clone());
+                    }
+                }
+            }
+        }
+
+        if required.is_empty() {
+            return None;
+        }
+
+        let parent = path.parent()?;
+        let siblings: Vec<String> = std::fs::read_dir(parent)
+            .map(|entries| {
+                entries
+                    .filter_map(Result::ok)
+                    .map(|entry| entry.file_name().to_string_lossy().into_owned())
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let found = siblings
+            .iter()
+            .any(|name| required.iter().any(|marker| ecosystems::matches_marker(name, marker)));
+
+        if found {
+            None
+        } else {
+            Some(required)
+        }
+    }
+
     #[inline]
     fn should_clean(
         path: &Path,
@@ -417,3 +1077,75 @@ impl Processor {
         }
     }
 }
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::config::Patterns;
+
+    /// A fresh directory for one test.
+    fn scratch(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("project-cleaner-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// Clean `app/target` from two directories in one run, as repeated
+    /// `--dir` flags do, and check both backups survive side by side.
+    async fn two_dirs_share_a_backup_dir(name: &str, versioning: bool, format: BackupFormat) {
+        let root = scratch(name);
+        let backup_dir = root.join("backup");
+        let dirs = [root.join("one/work"), root.join("two/work")];
+        for dir in &dirs {
+            std::fs::create_dir_all(dir.join("app/target")).unwrap();
+            std::fs::write(dir.join("app/target/bin"), dir.to_string_lossy().as_bytes()).unwrap();
+        }
+
+        for dir in &dirs {
+            let mut config = Config {
+                dir: dir.to_string_lossy().into_owned(),
+                target: Patterns { dirs_patterns: vec!["target$".to_string()], ..Patterns::default() },
+                ..Config::default()
+            };
+            config.backup.dir = backup_dir.to_string_lossy().into_owned();
+            config.backup.versioning = versioning;
+            config.backup.format = format;
+            Processor::new(config).interaction(Interaction::new(true, true)).process().await.unwrap();
+            assert!(!dir.join("app/target").exists());
+        }
+
+        let version_paths = if versioning {
+            versions::list(&backup_dir).await.unwrap().into_iter().map(|version| version.path).collect()
+        } else {
+            vec![backup_dir.clone()]
+        };
+        let mut restored = Vec::new();
+        for version_path in version_paths {
+            for item in manifest::read(&manifest::for_version(&version_path)).unwrap().items {
+                let stored = versions::digests(&version_path, std::slice::from_ref(&item.backup_path)).unwrap();
+                assert_eq!(stored[&item.backup_path].checksum, item.checksum, "{}", item.backup_path.display());
+                restored.push(item.original_path);
+            }
+        }
+        restored.sort();
+        assert_eq!(restored, dirs.iter().map(|dir| dir.join("app/target")).collect::<Vec<_>>());
+
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[tokio::test]
+    async fn two_dirs_unversioned() {
+        two_dirs_share_a_backup_dir("two-dirs-unversioned", false, BackupFormat::Tree).await;
+    }
+
+    #[tokio::test]
+    async fn two_dirs_versioned() {
+        two_dirs_share_a_backup_dir("two-dirs-versioned", true, BackupFormat::Tree).await;
+    }
+
+    #[tokio::test]
+    async fn two_dirs_in_one_archive_run() {
+        two_dirs_share_a_backup_dir("two-dirs-archive", true, BackupFormat::TarZst).await;
+    }
+}
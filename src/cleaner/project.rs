@@ -0,0 +1,31 @@
+use std::path::{Path, PathBuf};
+
+use crate::ecosystems::ECOSYSTEMS;
+
+/// Whether `dir` looks like the root of a project: a git checkout or a
+/// directory holding the manifest of a known ecosystem.
+fn is_project_root(dir: &Path) -> bool {
+    if dir.join(".git").exists() {
+        return true;
+    }
+    std::fs::read_dir(dir)
+        .map(|entries| {
+            entries.filter_map(Result::ok).any(|entry| {
+                let file_name = entry.file_name();
+                let file_name = file_name.to_string_lossy();
+                ECOSYSTEMS.iter().any(|ecosystem| ecosystem.is_marker(&file_name))
+            })
+        })
+        .unwrap_or(false)
+}
+
+/// The project `path` belongs to: its closest ancestor, up to `source_dir`,
+/// that is a project root, or `source_dir` itself when there is none.
+pub fn project_root(path: &Path, source_dir: &Path) -> PathBuf {
+    path.ancestors()
+        .skip(1)
+        .take_while(|ancestor| ancestor.starts_with(source_dir))
+        .find(|ancestor| is_project_root(ancestor))
+        .unwrap_or(source_dir)
+        .to_path_buf()
+}
@@ -0,0 +1,244 @@
+use chrono::{Datelike, NaiveDateTime};
+use humansize::{format_size, BINARY};
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+
+use super::versions::{BackupVersion, TIMESTAMP_FORMAT};
+use crate::config::units::{format_duration, parse_duration, parse_size};
+use crate::config::RetentionConfig;
+
+/// Period a version falls in for a `keep_*` rule.
+type PeriodOf = fn(&NaiveDateTime) -> String;
+
+/// Disk space taken by one version.
+#[derive(Default)]
+pub struct VersionUsage {
+    /// Bytes belonging to the version alone.
+    pub own: u64,
+    /// Dedup blobs it refers to, which other versions may share.
+    pub blobs: HashMap<String, u64>,
+}
+
+/// Whether `prune` keeps a version, and why.
+pub struct Decision {
+    pub keep: bool,
+    pub reason: String,
+}
+
+/// Compiled form of `RetentionConfig`.
+pub struct RetentionPolicy {
+    keep_last: Option<usize>,
+    keep_daily: Option<usize>,
+    keep_weekly: Option<usize>,
+    keep_monthly: Option<usize>,
+    max_age: Option<Duration>,
+    max_total_size: Option<u64>,
+}
+
+impl RetentionPolicy {
+    /// Expects a validated configuration.
+    pub fn new(config: &RetentionConfig) -> Self {
+        Self {
+            keep_last: config.keep_last,
+            keep_daily: config.keep_daily,
+            keep_weekly: config.keep_weekly,
+            keep_monthly: config.keep_monthly,
+            max_age: config.max_age.as_deref().map(|age| parse_duration(age).unwrap()),
+            max_total_size: config.max_total_size.as_deref().map(|size| parse_size(size).unwrap()),
+        }
+    }
+
+    /// Space used by the versions for which `kept` returns true, counting
+    /// shared blobs once.
+    pub fn total_size(usages: &[VersionUsage], kept: impl Fn(usize) -> bool) -> u64 {
+        let mut blobs = HashSet::new();
+        let mut total = 0;
+        for (index, usage) in usages.iter().enumerate() {
+            if !kept(index) {
+                continue;
+            }
+            total += usage.own;
+            for (hash, size) in &usage.blobs {
+                if blobs.insert(hash) {
+                    total += size;
+                }
+            }
+        }
+        total
+    }
+
+    /// Decide for each of `versions` (newest first, with their `usages`)
+    /// whether it is kept.
+    pub fn apply(&self, versions: &[BackupVersion], usages: &[VersionUsage], now: NaiveDateTime) -> Vec<Decision> {
+        let mut decisions: Vec<Decision> = versions
+            .iter()
+            .map(|_| Decision { keep: false, reason: "not kept by any rule".to_string() })
+            .collect();
+
+        let keep_rules: [(Option<usize>, &str, PeriodOf); 4] = [
+            (self.keep_last, "last", |timestamp| timestamp.format(TIMESTAMP_FORMAT).to_string()),
+            (self.keep_daily, "daily", |timestamp| timestamp.format("%Y-%m-%d").to_string()),
+            (self.keep_weekly, "weekly", |timestamp| {
+                let week = timestamp.iso_week();
+                format!("{}-W{:02}", week.year(), week.week())
+            }),
+            (self.keep_monthly, "monthly", |timestamp| timestamp.format("%Y-%m").to_string()),
+        ];
+        if keep_rules.iter().all(|(count, _, _)| count.is_none()) {
+            for decision in &mut decisions {
+                *decision = Decision { keep: true, reason: "no keep rule set".to_string() };
+            }
+        }
+        for (count, name, period_of) in keep_rules {
+            let Some(count) = count else { continue };
+            // The newest version of each period, for the `count` newest periods
+            let mut periods = HashSet::new();
+            for (version, decision) in versions.iter().zip(&mut decisions) {
+                if periods.len() == count {
+                    break;
+                }
+                if !periods.insert(period_of(&version.timestamp)) {
+                    continue;
+                }
+                if decision.keep {
+                    decision.reason.push_str(&format!(", {}", name));
+                } else {
+                    *decision = Decision { keep: true, reason: name.to_string() };
+                }
+            }
+        }
+
+        if let Some(max_age) = self.max_age {
+            for (version, decision) in versions.iter().zip(&mut decisions) {
+                let age = (now - version.timestamp).to_std().unwrap_or_default();
+                if decision.keep && age > max_age {
+                    *decision = Decision { keep: false, reason: format!("older than {}", format_duration(max_age)) };
+                }
+            }
+        }
+
+        if let Some(max_total_size) = self.max_total_size {
+            // Oldest first, until what is left fits
+            for index in (0..versions.len()).rev() {
+                if Self::total_size(usages, |index| decisions[index].keep) <= max_total_size {
+                    break;
+                }
+                if decisions[index].keep {
+                    decisions[index] = Decision {
+                        keep: false,
+                        reason: format!("over the {} size limit", format_size(max_total_size, BINARY)),
+                    };
+                }
+            }
+        }
+
+        if let Some(newest) = decisions.first_mut() {
+            if !newest.keep {
+                *newest = Decision { keep: true, reason: "newest version".to_string() };
+            }
+        }
+        decisions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn version(name: &str) -> BackupVersion {
+        BackupVersion {
+            name: name.to_string(),
+            timestamp: NaiveDateTime::parse_from_str(name, TIMESTAMP_FORMAT).unwrap(),
+            path: PathBuf::from(name),
+        }
+    }
+
+    /// Versions newest first, each using `own` bytes, with what `config`
+    /// decides about them at `now`.
+    fn kept(config: RetentionConfig, names: &[&str], own: u64, now: &str) -> Vec<bool> {
+        let versions: Vec<BackupVersion> = names.iter().map(|name| version(name)).collect();
+        let usages: Vec<VersionUsage> = names.iter().map(|_| VersionUsage { own, blobs: HashMap::new() }).collect();
+        let now = NaiveDateTime::parse_from_str(now, TIMESTAMP_FORMAT).unwrap();
+        RetentionPolicy::new(&config)
+            .apply(&versions, &usages, now)
+            .into_iter()
+            .map(|decision| decision.keep)
+            .collect()
+    }
+
+    const NOW: &str = "20240315_120000";
+
+    #[test]
+    fn keeps_everything_without_rules() {
+        let names = ["20240301_000000", "20230101_000000", "20200101_000000"];
+        assert_eq!(kept(RetentionConfig::default(), &names, 1, NOW), [true, true, true]);
+    }
+
+    #[test]
+    fn keep_last() {
+        let config = RetentionConfig { keep_last: Some(2), ..RetentionConfig::default() };
+        let names = ["20240303_000000", "20240302_000000", "20240301_000000"];
+        assert_eq!(kept(config, &names, 1, NOW), [true, true, false]);
+    }
+
+    #[test]
+    fn keep_daily_takes_the_newest_of_each_day() {
+        let config = RetentionConfig { keep_daily: Some(2), ..RetentionConfig::default() };
+        let names = ["20240303_180000", "20240303_090000", "20240302_120000", "20240301_120000"];
+        assert_eq!(kept(config, &names, 1, NOW), [true, false, true, false]);
+    }
+
+    #[test]
+    fn keep_weekly_uses_iso_weeks() {
+        let config = RetentionConfig { keep_weekly: Some(2), ..RetentionConfig::default() };
+        // Sunday 10 March and Monday 4 March share a week, Sunday 3 March does not
+        let names = ["20240310_000000", "20240304_000000", "20240303_000000", "20240226_000000"];
+        assert_eq!(kept(config, &names, 1, NOW), [true, false, true, false]);
+    }
+
+    #[test]
+    fn keep_monthly() {
+        let config = RetentionConfig { keep_monthly: Some(2), ..RetentionConfig::default() };
+        let names = ["20240310_000000", "20240301_000000", "20240229_000000", "20240131_000000"];
+        assert_eq!(kept(config, &names, 1, NOW), [true, false, true, false]);
+    }
+
+    #[test]
+    fn keep_rules_add_up() {
+        let config = RetentionConfig { keep_last: Some(1), keep_monthly: Some(3), ..RetentionConfig::default() };
+        let names = ["20240310_000000", "20240301_000000", "20240215_000000", "20240115_000000", "20231215_000000"];
+        assert_eq!(kept(config, &names, 1, NOW), [true, false, true, true, false]);
+    }
+
+    #[test]
+    fn max_age_removes_old_versions_but_never_the_newest() {
+        let config = RetentionConfig { max_age: Some("30d".to_string()), ..RetentionConfig::default() };
+        let names = ["20240301_000000", "20240101_000000"];
+        assert_eq!(kept(config.clone(), &names, 1, NOW), [true, false]);
+        assert_eq!(kept(config, &["20230101_000000"], 1, NOW), [true]);
+    }
+
+    #[test]
+    fn max_total_size_drops_the_oldest_first() {
+        let config = RetentionConfig { max_total_size: Some("250B".to_string()), ..RetentionConfig::default() };
+        let names = ["20240303_000000", "20240302_000000", "20240301_000000"];
+        assert_eq!(kept(config, &names, 100, NOW), [true, true, false]);
+
+        let config = RetentionConfig { max_total_size: Some("1B".to_string()), ..RetentionConfig::default() };
+        assert_eq!(kept(config, &names, 100, NOW), [true, false, false]);
+    }
+
+    #[test]
+    fn shared_blobs_count_once() {
+        let blobs: HashMap<String, u64> = [("a".to_string(), 100), ("b".to_string(), 50)].into();
+        let usages = vec![
+            VersionUsage { own: 10, blobs: blobs.clone() },
+            VersionUsage { own: 10, blobs },
+            VersionUsage { own: 10, blobs: [("c".to_string(), 1)].into() },
+        ];
+        assert_eq!(RetentionPolicy::total_size(&usages, |_| true), 181);
+        assert_eq!(RetentionPolicy::total_size(&usages, |index| index < 2), 170);
+        assert_eq!(RetentionPolicy::total_size(&usages, |index| index == 2), 11);
+    }
+}
@@ -0,0 +1,92 @@
+use humansize::{format_size, BINARY};
+use regex::Regex;
+use std::cmp::Reverse;
+use std::path::Path;
+use std::time::SystemTime;
+
+use super::age::AgePolicy;
+use super::types::{CleanTarget, SkippedTarget};
+use crate::config::units::parse_size;
+use crate::config::{SelectionOrder, SizeConfig};
+
+/// Compiled form of `SizeConfig`, shared by the scanning tasks.
+pub struct SizePolicy {
+    min_size: Option<u64>,
+    rules: Vec<(Regex, u64)>,
+    max_total: Option<u64>,
+    order: SelectionOrder,
+}
+
+impl SizePolicy {
+    /// Expects a validated configuration.
+    pub fn new(config: &SizeConfig) -> Self {
+        Self {
+            min_size: config.min_size.as_deref().map(|size| parse_size(size).unwrap()),
+            rules: config
+                .rules
+                .iter()
+                .map(|rule| (Regex::new(&rule.pattern).unwrap(), parse_size(&rule.min_size).unwrap()))
+                .collect(),
+            max_total: config.max_total.as_deref().map(|size| parse_size(size).unwrap()),
+            order: config.order,
+        }
+    }
+
+    fn min_size_for(&self, relative_path: &Path) -> Option<u64> {
+        let path_str = relative_path.to_string_lossy();
+        self.rules
+            .iter()
+            .filter(|(pattern, _)| pattern.is_match(&path_str))
+            .map(|(_, size)| *size)
+            .max()
+            .or(self.min_size)
+    }
+
+    /// Why a target of `size` bytes is too small to be cleaned, if it is.
+    pub fn check(&self, size: u64, relative_path: &Path) -> Option<String> {
+        let min_size = self.min_size_for(relative_path)?;
+        if size >= min_size {
+            return None;
+        }
+        Some(format!(
+            "{}, smaller than {}",
+            format_size(size, BINARY),
+            format_size(min_size, BINARY)
+        ))
+    }
+
+    /// Keep targets in `order` until the run budget is used up; the rest are
+    /// returned as skipped. Without a budget every target is kept.
+    pub fn select(&self, mut items: Vec<CleanTarget>, age_policy: &AgePolicy) -> (Vec<CleanTarget>, Vec<SkippedTarget>) {
+        let max_total = match self.max_total {
+            Some(max_total) => max_total,
+            None => return (items, Vec::new()),
+        };
+
+        match self.order {
+            SelectionOrder::Largest => items.sort_by_key(|target| Reverse(target.size)),
+            SelectionOrder::Oldest => items.sort_by_cached_key(|target| {
+                age_policy
+                    .last_used(&target.source_path, None)
+                    .unwrap_or(SystemTime::UNIX_EPOCH)
+            }),
+        }
+
+        let mut selected = Vec::new();
+        let mut skipped = Vec::new();
+        let mut total = 0;
+        for target in items {
+            if total >= max_total {
+                skipped.push(SkippedTarget {
+                    source_path: target.source_path,
+                    reason: format!("run budget of {} reached", format_size(max_total, BINARY)),
+                });
+                continue;
+            }
+            total += target.size;
+            selected.push(target);
+        }
+
+        (selected, skipped)
+    }
+}
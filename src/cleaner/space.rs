@@ -0,0 +1,47 @@
+use std::io;
+use std::path::Path;
+
+/// Closest existing ancestor of `path`: where it would be created.
+fn existing_ancestor(path: &Path) -> &Path {
+    path.ancestors().find(|ancestor| ancestor.exists()).unwrap_or(path)
+}
+
+/// Bytes unprivileged users can still write on the filesystem that holds,
+/// or would hold, `path`.
+#[cfg(unix)]
+#[allow(clippy::useless_conversion)] // the statvfs field types vary between platforms
+pub fn available(path: &Path) -> io::Result<u64> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let path = CString::new(existing_ancestor(path).as_os_str().as_bytes())?;
+    let mut stat = std::mem::MaybeUninit::<libc::statvfs>::uninit();
+    // SAFETY: `path` is NUL-terminated and `stat` is only read after statvfs filled it in
+    if unsafe { libc::statvfs(path.as_ptr(), stat.as_mut_ptr()) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let stat = unsafe { stat.assume_init() };
+    Ok(u64::from(stat.f_bavail) * u64::from(stat.f_frsize))
+}
+
+#[cfg(not(unix))]
+pub fn available(_path: &Path) -> io::Result<u64> {
+    Ok(u64::MAX)
+}
+
+/// Whether `source` can be renamed to somewhere below `dir`, which may not
+/// exist yet.
+#[cfg(unix)]
+pub fn same_filesystem(source: &Path, dir: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+
+    match (source.symlink_metadata(), existing_ancestor(dir).metadata()) {
+        (Ok(source), Ok(dir)) => source.dev() == dir.dev(),
+        _ => false,
+    }
+}
+
+#[cfg(not(unix))]
+pub fn same_filesystem(_source: &Path, _dir: &Path) -> bool {
+    false
+}
@@ -0,0 +1,298 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use walkdir::WalkDir;
+
+use super::archive::check_relative;
+use super::attributes::{clear, symlink, Attributes, HardLinks};
+use super::checksum::{self, Digest, TreeEntry};
+use super::types::BackupEntry;
+
+/// Suffix of the manifest describing one version in the store.
+pub const MANIFEST_EXTENSION: &str = "dedup.json";
+
+/// Directory below the backup directory holding the blobs, by BLAKE3 hash.
+const OBJECTS_DIR: &str = "objects";
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum EntryKind {
+    File,
+    Directory,
+    Symlink,
+}
+
+/// One path of a stored version. Files point at their blob by `hash`.
+#[derive(Serialize, Deserialize)]
+pub struct StoreEntry {
+    pub path: PathBuf,
+    pub kind: EntryKind,
+    pub size: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hash: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub link_target: Option<PathBuf>,
+    /// Earlier entry of the same item this file is a hard link to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hard_link: Option<PathBuf>,
+    /// Absent from manifests written before attributes were kept.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attributes: Option<Attributes>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct StoreManifest {
+    pub entries: Vec<StoreEntry>,
+}
+
+pub fn is_manifest(path: &Path) -> bool {
+    path.to_string_lossy().ends_with(&format!(".{}", MANIFEST_EXTENSION))
+}
+
+fn blob_path(store: &Path, hash: &str) -> PathBuf {
+    store.join(OBJECTS_DIR).join(&hash[..2]).join(&hash[2..])
+}
+
+/// Add the file at `source` to the store unless identical content is
+/// already there, returning its hash. The hash is taken from the very bytes
+/// that are copied, so a file changing meanwhile cannot end up under the
+/// wrong name.
+fn store_file(store: &Path, source: &Path) -> io::Result<String> {
+    static PARTIALS: AtomicUsize = AtomicUsize::new(0);
+
+    let objects = store.join(OBJECTS_DIR);
+    fs::create_dir_all(&objects)?;
+    // Copy under a temporary name so a blob is never seen half-written
+    let partial = objects.join(format!(
+        ".{}-{}.partial",
+        std::process::id(),
+        PARTIALS.fetch_add(1, Ordering::Relaxed)
+    ));
+    let hash = match copy_hashing(source, &partial) {
+        Ok(hash) => hash,
+        Err(e) => {
+            let _ = fs::remove_file(&partial);
+            return Err(e);
+        }
+    };
+
+    let blob = blob_path(store, &hash);
+    if blob.exists() {
+        fs::remove_file(&partial)?;
+    } else {
+        fs::create_dir_all(blob.parent().expect("blob paths have a parent"))?;
+        fs::rename(&partial, &blob)?;
+    }
+    Ok(hash)
+}
+
+/// Copy `source` to a new file at `destination`, returning the BLAKE3 hash
+/// of what was written.
+fn copy_hashing(source: &Path, destination: &Path) -> io::Result<String> {
+    let mut reader = File::open(source)?;
+    let mut writer = File::create_new(destination)?;
+    let mut hasher = blake3::Hasher::new();
+    let mut buffer = vec![0; 64 * 1024];
+    loop {
+        let read = match reader.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        hasher.update(&buffer[..read]);
+        writer.write_all(&buffer[..read])?;
+    }
+    writer.sync_all()?;
+    Ok(hasher.finalize().to_hex().to_string())
+}
+
+/// Store `items` (source path, path relative to the project directory) in the
+/// store at `store` and describe them in a new manifest at `manifest_path`,
+/// which must not exist yet.
+pub fn write(store: &Path, manifest_path: &Path, items: &[(PathBuf, PathBuf)]) -> io::Result<()> {
+    let already_exists = || {
+        io::Error::new(io::ErrorKind::AlreadyExists, format!("{} already exists", manifest_path.display()))
+    };
+    if manifest_path.symlink_metadata().is_ok() {
+        return Err(already_exists());
+    }
+    let mut manifest = StoreManifest::default();
+
+    for (source, name) in items {
+        // Hard links are tracked per item, as in archives
+        let mut links = HardLinks::default();
+        for entry in WalkDir::new(source).follow_root_links(false) {
+            let entry = entry?;
+            let relative = entry.path().strip_prefix(source).expect("walked paths are below their root");
+            // Joining an empty path would add a trailing `/`
+            let path = if relative.as_os_str().is_empty() { name.clone() } else { name.join(relative) };
+            let attributes = Some(Attributes::read(entry.path())?);
+            let metadata = entry.path().symlink_metadata()?;
+
+            let (kind, size, hash, link_target, hard_link) = if metadata.is_symlink() {
+                (EntryKind::Symlink, 0, None, Some(fs::read_link(entry.path())?), None)
+            } else if metadata.is_dir() {
+                (EntryKind::Directory, 0, None, None, None)
+            } else {
+                let hard_link = links.first(&metadata, &path);
+                (EntryKind::File, metadata.len(), Some(store_file(store, entry.path())?), None, hard_link)
+            };
+            manifest.entries.push(StoreEntry { path, kind, size, hash, link_target, hard_link, attributes });
+        }
+    }
+
+    let partial = manifest_path.with_extension("partial");
+    let mut writer = BufWriter::new(File::create_new(&partial)?);
+    serde_json::to_writer_pretty(&mut writer, &manifest)?;
+    writer.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+    // Renaming replaces silently, so look again for a version written meanwhile
+    if manifest_path.symlink_metadata().is_ok() {
+        fs::remove_file(&partial)?;
+        return Err(already_exists());
+    }
+    fs::rename(&partial, manifest_path)
+}
+
+pub fn read(manifest_path: &Path) -> io::Result<StoreManifest> {
+    let file = File::open(manifest_path)?;
+    serde_json::from_reader(io::BufReader::new(file)).map_err(io::Error::from)
+}
+
+pub fn list(manifest_path: &Path) -> io::Result<Vec<BackupEntry>> {
+    Ok(read(manifest_path)?
+        .entries
+        .into_iter()
+        .map(|entry| BackupEntry {
+            path: entry.path,
+            is_directory: entry.kind == EntryKind::Directory,
+            size: entry.size,
+        })
+        .collect())
+}
+
+/// The blobs the version at `manifest_path` refers to, with their sizes.
+pub fn blobs(manifest_path: &Path) -> io::Result<HashMap<String, u64>> {
+    Ok(read(manifest_path)?
+        .entries
+        .into_iter()
+        .filter_map(|entry| Some((entry.hash?, entry.size)))
+        .collect())
+}
+
+/// Delete the blobs of the store at `store` that are not in `referenced`,
+/// returning how many there were and their size.
+pub fn collect_garbage(store: &Path, referenced: &HashSet<String>) -> io::Result<(usize, u64)> {
+    let objects = store.join(OBJECTS_DIR);
+    let (mut count, mut size) = (0, 0);
+    if !objects.exists() {
+        return Ok((count, size));
+    }
+
+    // Leftovers of interrupted copies, directly in `objects/`, are garbage too
+    for entry in WalkDir::new(&objects).min_depth(1).max_depth(2) {
+        let entry = entry?;
+        let prefix = entry.path().parent().and_then(Path::file_name).unwrap_or_default();
+        let hash = format!("{}{}", prefix.to_string_lossy(), entry.file_name().to_string_lossy());
+        if !entry.file_type().is_file() || (entry.depth() == 2 && referenced.contains(&hash)) {
+            continue;
+        }
+
+        count += 1;
+        size += entry.metadata()?.len();
+        fs::remove_file(entry.path())?;
+    }
+    Ok((count, size))
+}
+
+/// Rebuild every entry of the version at `manifest_path` that
+/// `destination_of` maps to a path there, returning how many were written.
+pub fn extract(
+    store: &Path,
+    manifest_path: &Path,
+    destination_of: impl Fn(&Path) -> Option<PathBuf>,
+) -> io::Result<usize> {
+    let mut restored: HashMap<PathBuf, PathBuf> = HashMap::new();
+    let mut rebuilt = Vec::new();
+    for entry in read(manifest_path)?.entries {
+        check_relative(&entry.path)?;
+        let target = match destination_of(&entry.path) {
+            Some(target) => target,
+            None => continue,
+        };
+
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        match entry.kind {
+            EntryKind::Directory => {
+                if target.symlink_metadata().is_ok_and(|existing| !existing.is_dir()) {
+                    fs::remove_file(&target)?;
+                }
+                fs::create_dir_all(&target)?;
+            }
+            EntryKind::File => {
+                clear(&target)?;
+                match entry.hard_link.as_ref().and_then(|first| restored.get(first)) {
+                    Some(first) => fs::hard_link(first, &target)?,
+                    None => {
+                        let hash = entry.hash.ok_or_else(|| {
+                            io::Error::new(io::ErrorKind::InvalidData, format!("no blob for {}", entry.path.display()))
+                        })?;
+                        fs::copy(blob_path(store, &hash), &target)?;
+                    }
+                }
+            }
+            EntryKind::Symlink => {
+                clear(&target)?;
+                symlink(&entry.link_target.unwrap_or_default(), &target)?;
+            }
+        }
+        restored.insert(entry.path, target.clone());
+        rebuilt.push((target, entry.attributes));
+    }
+
+    // In reverse, as in `attributes::copy`
+    for (target, attributes) in rebuilt.iter().rev() {
+        if let Some(attributes) = attributes {
+            attributes.apply(target)?;
+        }
+    }
+    Ok(rebuilt.len())
+}
+
+/// Digests of `items` (paths inside the version) as the store holds them,
+/// hashing every blob again so damaged ones show. Items the version does not
+/// contain are left out.
+pub fn digests(store: &Path, manifest_path: &Path, items: &[PathBuf]) -> io::Result<HashMap<PathBuf, Digest>> {
+    let mut entries: HashMap<PathBuf, Vec<TreeEntry>> = HashMap::new();
+    for entry in read(manifest_path)?.entries {
+        let item = match items.iter().find(|item| entry.path.starts_with(item)) {
+            Some(item) => item,
+            None => continue,
+        };
+        let relative = entry.path.strip_prefix(item).expect("matched by prefix").to_path_buf();
+
+        let (size, content) = match entry.kind {
+            EntryKind::File => {
+                // A missing blob hashes to nothing, which never matches
+                let blob = entry.hash.filter(|hash| hash.len() > 2).map(|hash| blob_path(store, &hash));
+                match blob.filter(|blob| blob.is_file()) {
+                    Some(blob) => (fs::metadata(&blob)?.len(), checksum::file(&blob)?),
+                    None => (0, String::new()),
+                }
+            }
+            EntryKind::Directory => (0, String::new()),
+            EntryKind::Symlink => (0, entry.link_target.unwrap_or_default().to_string_lossy().into_owned()),
+        };
+        entries.entry(item.clone()).or_default().push(TreeEntry { relative, kind: entry.kind, size, content });
+    }
+
+    Ok(entries
+        .into_iter()
+        .map(|(item, entries)| (item, checksum::combine(entries)))
+        .collect())
+}
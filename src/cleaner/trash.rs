@@ -0,0 +1,124 @@
+use std::collections::HashSet;
+use std::ffi::OsString;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use super::space;
+
+/// The home trash of the freedesktop.org Trash spec, `$XDG_DATA_HOME/Trash`.
+pub fn home_trash() -> Option<PathBuf> {
+    let data_home = match std::env::var_os("XDG_DATA_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(std::env::var_os("HOME")?).join(".local/share"),
+    };
+    Some(data_home.join("Trash"))
+}
+
+/// The trash `path` is moved to, so trashing never has to copy: the home
+/// trash when both are on the same filesystem, otherwise the trash at the top
+/// of the filesystem holding `path`. That is `$topdir/.Trash/$uid` when an
+/// administrator set up `.Trash` the way the spec describes, or else
+/// `$topdir/.Trash-$uid`.
+#[cfg(unix)]
+pub fn trash_for(path: &Path, home_trash: &Path) -> PathBuf {
+    use std::os::unix::fs::{MetadataExt, PermissionsExt};
+
+    let device = match path.symlink_metadata() {
+        Ok(metadata) if !space::same_filesystem(path, home_trash) => metadata.dev(),
+        _ => return home_trash.to_path_buf(),
+    };
+    let top_dir = path
+        .ancestors()
+        .skip(1)
+        .take_while(|ancestor| ancestor.metadata().is_ok_and(|metadata| metadata.dev() == device))
+        .last();
+    // A mount point itself cannot be renamed into a trash on its own filesystem
+    let top_dir = match top_dir {
+        Some(top_dir) => top_dir,
+        None => return home_trash.to_path_buf(),
+    };
+
+    let uid = unsafe { libc::getuid() };
+    let shared = top_dir.join(".Trash");
+    // Only a real directory with the sticky bit, so no other user can swap it for a link
+    let usable = shared
+        .symlink_metadata()
+        .is_ok_and(|metadata| metadata.is_dir() && metadata.permissions().mode() & 0o1000 != 0);
+    if usable {
+        shared.join(uid.to_string())
+    } else {
+        top_dir.join(format!(".Trash-{}", uid))
+    }
+}
+
+#[cfg(not(unix))]
+pub fn trash_for(_path: &Path, home_trash: &Path) -> PathBuf {
+    home_trash.to_path_buf()
+}
+
+/// Create whatever is missing of `trash` and its `files` and `info`
+/// directories, accessible to the user alone as the spec asks.
+pub fn create(trash: &Path) -> io::Result<()> {
+    for subdir in ["files", "info"] {
+        let mut builder = std::fs::DirBuilder::new();
+        builder.recursive(true);
+        #[cfg(unix)]
+        std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+        builder.create(trash.join(subdir))?;
+    }
+    Ok(())
+}
+
+/// Where the `.trashinfo` file of the trashed item `name` lives.
+pub fn info_path(trash: &Path, name: &OsString) -> PathBuf {
+    let mut file_name = name.clone();
+    file_name.push(".trashinfo");
+    trash.join("info").join(file_name)
+}
+
+/// A name for `path` that is free in the trash and not already handed out to
+/// another item of the same run: `target`, then `target.2`, `target.3`...
+pub fn unique_name(trash: &Path, path: &Path, taken: &mut HashSet<OsString>) -> OsString {
+    let base = path.file_name().map(OsString::from).unwrap_or_else(|| "item".into());
+    let mut name = base.clone();
+    let mut counter = 1;
+    while taken.contains(&name)
+        || trash.join("files").join(&name).symlink_metadata().is_ok()
+        || info_path(trash, &name).symlink_metadata().is_ok()
+    {
+        counter += 1;
+        name = base.clone();
+        name.push(format!(".{}", counter));
+    }
+    taken.insert(name.clone());
+    name
+}
+
+/// Contents of the `.trashinfo` file recording where `original` came from.
+/// The home trash needs absolute paths, so a relative `original` is resolved
+/// against the current directory.
+pub fn info_contents(original: &Path) -> String {
+    let original = std::path::absolute(original).unwrap_or_else(|_| original.to_path_buf());
+    format!(
+        "[Trash Info]\nPath={}\nDeletionDate={}\n",
+        encode_path(&original),
+        chrono::Local::now().format("%Y-%m-%dT%H:%M:%S")
+    )
+}
+
+/// Percent-encode the bytes of a path the way the spec asks for, keeping `/`
+/// readable; names that are not UTF-8 survive unchanged.
+fn encode_path(path: &Path) -> String {
+    #[cfg(unix)]
+    let bytes = std::os::unix::ffi::OsStrExt::as_bytes(path.as_os_str()).to_vec();
+    #[cfg(not(unix))]
+    let bytes = path.to_string_lossy().into_owned().into_bytes();
+
+    bytes
+        .into_iter()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => (byte as char).to_string(),
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
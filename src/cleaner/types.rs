@@ -1,11 +1,40 @@
 use colored::*;
 use humansize::{format_size, BINARY};
-use std::path::PathBuf;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
 
 #[derive(Debug, Clone)]
 pub struct CleanTarget {
     pub source_path: PathBuf,
+    /// Root of the project the target belongs to.
+    pub project: PathBuf,
     pub is_directory: bool,
+    pub size: u64,
+}
+
+/// How projects are ordered in the summary.
+#[derive(Debug, Clone, Copy, Default)]
+pub enum ProjectOrder {
+    #[default]
+    Name,
+    /// Most space to reclaim first.
+    Size,
+    /// Most targets first.
+    Items,
+}
+
+/// The targets of one project, with their combined size.
+pub struct ProjectGroup<'a> {
+    pub project: &'a Path,
+    pub items: Vec<&'a CleanTarget>,
+    pub size: u64,
+}
+
+/// A path that matched a target pattern but was left alone, and why.
+#[derive(Debug, Clone)]
+pub struct SkippedTarget {
+    pub source_path: PathBuf,
+    pub reason: String,
 }
 
 #[derive(Debug)]
@@ -14,6 +43,10 @@ pub struct CleanSummary {
     pub total_size: u64,
     pub source_total_size: u64,
     pub items: Vec<CleanTarget>,
+    pub skipped: Vec<SkippedTarget>,
+    pub project_order: ProjectOrder,
+    /// Projects with targets that were left out by the project selection.
+    pub unselected_projects: usize,
 }
 
 impl CleanSummary {
@@ -21,23 +54,76 @@ impl CleanSummary {
         format_size(size, BINARY)
     }
 
+    /// Targets grouped by project, in `project_order`.
+    pub fn projects(&self) -> Vec<ProjectGroup<'_>> {
+        let mut groups: Vec<ProjectGroup> = Vec::new();
+        for item in &self.items {
+            match groups.iter_mut().find(|group| group.project == item.project) {
+                Some(group) => {
+                    group.items.push(item);
+                    group.size += item.size;
+                }
+                None => groups.push(ProjectGroup {
+                    project: &item.project,
+                    items: vec![item],
+                    size: item.size,
+                }),
+            }
+        }
+
+        groups.sort_by(|a, b| a.project.cmp(b.project));
+        match self.project_order {
+            ProjectOrder::Name => {}
+            ProjectOrder::Size => groups.sort_by_key(|group| std::cmp::Reverse(group.size)),
+            ProjectOrder::Items => groups.sort_by_key(|group| std::cmp::Reverse(group.items.len())),
+        }
+        groups
+    }
+
+    /// Targets in the order the summary lists them.
+    pub fn ordered_items(&self) -> Vec<&CleanTarget> {
+        self.projects().into_iter().flat_map(|group| group.items).collect()
+    }
+
     pub fn display_summary(&self) {
+        if !self.skipped.is_empty() {
+            println!("\n{}:", "⏭️  Skipped".bright_yellow().bold());
+            for skipped in &self.skipped {
+                println!(
+                    "  {} {} {}",
+                    "→".bright_black(),
+                    skipped.source_path.display().to_string().bright_white(),
+                    format!("({})", skipped.reason).bright_black()
+                );
+            }
+        }
+
         if self.total_items == 0 {
             println!("\n{}", "⚠️ No items to clean.".bright_yellow());
             return;
         }
 
+        let projects = self.projects();
         println!("\n{}:", "📋 Items to clean".bright_blue().bold());
-        for item in &self.items {
-            let icon = if item.is_directory { "📁" } else { "📄" };
-            let item_type = if item.is_directory { "Directory" } else { "File" };
+        for group in &projects {
             println!(
-                "  {} {} {}: {}",
-                "→".bright_black(),
-                icon,
-                item_type.bright_magenta(),
-                item.source_path.display().to_string().bright_white()
+                "\n  {} {} {}",
+                "📦".bold(),
+                group.project.display().to_string().bright_cyan().bold(),
+                format!("({} item(s), {})", group.items.len(), Self::format_size(group.size)).bright_black()
             );
+            for item in &group.items {
+                let icon = if item.is_directory { "📁" } else { "📄" };
+                let item_type = if item.is_directory { "Directory" } else { "File" };
+                println!(
+                    "    {} {} {}: {} {}",
+                    "→".bright_black(),
+                    icon,
+                    item_type.bright_magenta(),
+                    item.source_path.display().to_string().bright_white(),
+                    format!("({})", Self::format_size(item.size)).bright_black()
+                );
+            }
         }
 
         let reduction_percentage = if self.source_total_size > 0 {
@@ -47,6 +133,16 @@ impl CleanSummary {
         };
 
         println!("\n{}:", "📊 Clean summary".bright_blue().bold());
+        println!(
+            "  {} Projects: {}{}",
+            "→".bright_black(),
+            projects.len().to_string().bright_white(),
+            if self.unselected_projects > 0 {
+                format!(" ({} not selected)", self.unselected_projects).bright_black().to_string()
+            } else {
+                String::new()
+            }
+        );
         println!(
             "  {} Total items: {}",
             "→".bright_black(),
@@ -74,3 +170,117 @@ impl CleanSummary {
         );
     }
 }
+
+
+/// An entry of a stored backup version, relative to the project directory.
+#[derive(Debug, Clone)]
+pub struct BackupEntry {
+    pub path: PathBuf,
+    pub is_directory: bool,
+    pub size: u64,
+}
+
+/// A single planned removal, with the backup destination it would be copied to.
+#[derive(Debug, Clone, Serialize)]
+pub struct CleanOperation {
+    pub source_path: PathBuf,
+    pub project: PathBuf,
+    pub is_directory: bool,
+    pub size: u64,
+    pub backup_path: Option<PathBuf>,
+    /// `.trashinfo` file to write when moving to the trash.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trash_info: Option<PathBuf>,
+    pub remove_existing_backup: bool,
+}
+
+/// Everything `execute_cleanup` is going to do, computed up front so it can be
+/// reviewed (`--dry-run`) without touching the filesystem.
+#[derive(Debug, Clone, Serialize)]
+pub struct CleanPlan {
+    pub backup_dir: Option<PathBuf>,
+    pub create_backup_dir: bool,
+    /// Single file the run is written to: the archive of the `tar.zst`
+    /// format or the manifest of the `dedup` format.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version_file: Option<PathBuf>,
+    /// Manifest recording what the run backed up.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub manifest: Option<PathBuf>,
+    pub total_size: u64,
+    pub operations: Vec<CleanOperation>,
+}
+
+impl CleanPlan {
+    /// The same removals, with nothing backed up.
+    pub fn without_backup(&self) -> CleanPlan {
+        CleanPlan {
+            backup_dir: None,
+            create_backup_dir: false,
+            version_file: None,
+            manifest: None,
+            total_size: self.total_size,
+            operations: self
+                .operations
+                .iter()
+                .map(|operation| CleanOperation {
+                    backup_path: None,
+                    trash_info: None,
+                    remove_existing_backup: false,
+                    ..operation.clone()
+                })
+                .collect(),
+        }
+    }
+
+    pub fn display_plan(&self) {
+        println!("\n{}:", "🧪 Dry run plan".bright_blue().bold());
+
+        match &self.backup_dir {
+            Some(dir) => {
+                println!(
+                    "  {} Backup directory: {}{}",
+                    "→".bright_black(),
+                    dir.display().to_string().bright_white(),
+                    if self.create_backup_dir { " (would be created)".yellow().to_string() } else { String::new() }
+                );
+            }
+            None => {
+                println!("  {} {}", "→".bright_black(), "Backup disabled, items would be deleted directly".yellow());
+            }
+        }
+        if let Some(version_file) = &self.version_file {
+            println!("  {} Written to: {}", "→".bright_black(), version_file.display().to_string().bright_white());
+        }
+
+        for operation in &self.operations {
+            let item_type = if operation.is_directory { "directory" } else { "file" };
+            println!("\n  {} Remove {}: {} ({})",
+                "🗑️".bold(),
+                item_type,
+                operation.source_path.display().to_string().bright_white(),
+                format_size(operation.size, BINARY).bright_white()
+            );
+            if let Some(backup_path) = &operation.backup_path {
+                if operation.remove_existing_backup {
+                    println!("    {} Remove existing backup: {}", "→".bright_black(), backup_path.display().to_string().yellow());
+                }
+                let label = if operation.trash_info.is_some() { "Move to trash" } else { "Back up to" };
+                println!("    {} {}: {}", "→".bright_black(), label, backup_path.display().to_string().bright_cyan());
+            }
+        }
+
+        println!("\n{}:", "📊 Dry run summary".bright_blue().bold());
+        println!(
+            "  {} Operations: {}",
+            "→".bright_black(),
+            self.operations.len().to_string().bright_white()
+        );
+        println!(
+            "  {} Space to reclaim: {}",
+            "→".bright_black(),
+            format_size(self.total_size, BINARY).bright_white()
+        );
+        println!("{}", "ℹ️  Dry run: no files were modified.".bright_cyan());
+    }
+}
@@ -0,0 +1,354 @@
+use chrono::NaiveDateTime;
+use std::collections::{HashMap, HashSet};
+use std::io;
+use std::path::{Path, PathBuf};
+use tokio::fs;
+
+use super::checksum::{self, Digest};
+use super::retention::VersionUsage;
+use super::{archive, manifest, store};
+
+/// How versioned backups are named, as local time.
+pub const TIMESTAMP_FORMAT: &str = "%Y%m%d_%H%M%S";
+
+/// One run stored under a versioned backup directory.
+pub struct BackupVersion {
+    /// The timestamp as written in the name, e.g. `20240131_235959`, with
+    /// `_2`, `_3`, ... after it for later runs within the same second.
+    pub name: String,
+    pub timestamp: NaiveDateTime,
+    /// A timestamp directory, a `<timestamp>.tar.zst` archive or a
+    /// `<timestamp>.dedup.json` manifest.
+    pub path: PathBuf,
+}
+
+impl BackupVersion {
+    /// Every path making up the version, including its run manifest.
+    pub fn paths(&self) -> Vec<PathBuf> {
+        let mut paths = vec![self.path.clone()];
+        if self.path.is_file() {
+            paths.push(manifest::for_file(&self.path));
+        }
+        paths
+    }
+
+    /// Space the version takes, with the dedup blobs it refers to.
+    pub fn usage(&self) -> io::Result<VersionUsage> {
+        let mut usage = VersionUsage::default();
+        for path in self.paths() {
+            if path.is_dir() {
+                usage.own += fs_extra::dir::get_size(&path).map_err(|e| io::Error::other(e.to_string()))?;
+            } else if path.exists() {
+                usage.own += path.metadata()?.len();
+            }
+        }
+        if store::is_manifest(&self.path) {
+            usage.blobs = store::blobs(&self.path)?;
+        }
+        Ok(usage)
+    }
+
+    pub async fn remove(&self) -> io::Result<()> {
+        for path in self.paths() {
+            if path.is_dir() {
+                fs::remove_dir_all(&path).await?;
+            } else if path.exists() {
+                fs::remove_file(&path).await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The versions found in `backup_dir`, newest first.
+pub async fn list(backup_dir: &Path) -> io::Result<Vec<BackupVersion>> {
+    let mut versions = Vec::new();
+    if !backup_dir.exists() {
+        return Ok(versions);
+    }
+
+    let mut dir_reader = fs::read_dir(backup_dir).await?;
+    while let Some(entry) = dir_reader.next_entry().await? {
+        let path = entry.path();
+        let file_name = path.file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("")
+            .to_string();
+
+        let name = if path.is_dir() {
+            file_name
+        } else {
+            match [archive::EXTENSION, store::MANIFEST_EXTENSION]
+                .iter()
+                .find_map(|extension| file_name.strip_suffix(&format!(".{}", extension)))
+            {
+                Some(stem) => stem.to_string(),
+                None => continue,
+            }
+        };
+
+        if let Some((timestamp, _)) = parse_name(&name) {
+            versions.push(BackupVersion { name, timestamp, path });
+        }
+    }
+
+    versions.sort_by_key(|version| std::cmp::Reverse(parse_name(&version.name)));
+    Ok(versions)
+}
+
+/// Timestamp and sequence number of a version named `name`: 1 for a plain
+/// timestamp, 2 for `<timestamp>_2` and so on.
+pub fn parse_name(name: &str) -> Option<(NaiveDateTime, u32)> {
+    if let Ok(timestamp) = NaiveDateTime::parse_from_str(name, TIMESTAMP_FORMAT) {
+        return Some((timestamp, 1));
+    }
+    let (timestamp, sequence) = name.rsplit_once('_')?;
+    let sequence = sequence.parse().ok().filter(|number: &u32| *number > 1 && number.to_string() == sequence)?;
+    Some((NaiveDateTime::parse_from_str(timestamp, TIMESTAMP_FORMAT).ok()?, sequence))
+}
+
+/// Directory inside every backup that holds the items cleaned from
+/// `source_dir`: its name and a short hash of its path, so several project
+/// directories can share `backup.dir` without mixing or replacing each other's
+/// items.
+pub fn namespace(source_dir: &Path) -> PathBuf {
+    let name = source_dir.file_name().map(|name| name.to_string_lossy()).unwrap_or("root".into());
+    let hash = blake3::hash(source_dir.as_os_str().as_encoded_bytes()).to_hex();
+    PathBuf::from(format!("{}-{}", name, &hash[..8]))
+}
+
+/// Name for a new version at `timestamp` that no version in `backup_dir`
+/// uses yet, in any format, so runs within the same second never share one.
+pub fn unused_name(backup_dir: &Path, timestamp: &str) -> String {
+    (1..)
+        .map(|sequence: u32| if sequence == 1 { timestamp.to_string() } else { format!("{}_{}", timestamp, sequence) })
+        .find(|name| {
+            let archive = backup_dir.join(format!("{}.{}", name, archive::EXTENSION));
+            [
+                backup_dir.join(name),
+                backup_dir.join(format!("{}.{}", name, store::MANIFEST_EXTENSION)),
+                manifest::for_file(&archive),
+                archive,
+            ]
+            .iter()
+            .all(|path| path.symlink_metadata().is_err())
+        })
+        .expect("some sequence number is free")
+}
+
+/// Delete dedup blobs that no version left in `backup_dir` refers to,
+/// returning how many there were and their size.
+pub async fn collect_garbage(backup_dir: &Path) -> io::Result<(usize, u64)> {
+    let mut referenced = HashSet::new();
+    for version in list(backup_dir).await? {
+        if store::is_manifest(&version.path) {
+            referenced.extend(store::blobs(&version.path)?.into_keys());
+        }
+    }
+
+    let store_dir = backup_dir.to_path_buf();
+    tokio::task::spawn_blocking(move || store::collect_garbage(&store_dir, &referenced)).await?
+}
+
+/// Digests of `items` (paths inside the version) as stored in the version at
+/// `version_path`. Items it does not contain are left out.
+pub fn digests(version_path: &Path, items: &[PathBuf]) -> io::Result<HashMap<PathBuf, Digest>> {
+    if store::is_manifest(version_path) {
+        // Manifests sit next to the `objects/` directory of their store
+        let store_dir = version_path.parent().unwrap_or(Path::new("."));
+        return store::digests(store_dir, version_path, items);
+    }
+    if version_path.is_file() {
+        return archive::digests(version_path, items);
+    }
+
+    let mut digests = HashMap::new();
+    for item in items {
+        let path = version_path.join(item);
+        if path.symlink_metadata().is_ok() {
+            digests.insert(item.clone(), checksum::item(&path)?);
+        }
+    }
+    Ok(digests)
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::cleaner::attributes;
+    use filetime::FileTime;
+    use std::fs;
+    use std::os::unix::fs::{MetadataExt, PermissionsExt};
+
+    /// A fresh directory for one test.
+    fn scratch(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("project-cleaner-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    const MTIME: (i64, u32) = (1_000_000_000, 123_456_789);
+
+    /// A build directory with a hard link, a symlink, a nested directory and
+    /// set times and modes, plus a lone file; returns (source, name) pairs.
+    fn project(root: &Path) -> Vec<(PathBuf, PathBuf)> {
+        let target = root.join("app/target");
+        fs::create_dir_all(target.join("sub")).unwrap();
+        fs::write(target.join("bin"), b"binary").unwrap();
+        fs::set_permissions(target.join("bin"), fs::Permissions::from_mode(0o751)).unwrap();
+        fs::hard_link(target.join("bin"), target.join("bin-link")).unwrap();
+        std::os::unix::fs::symlink("bin", target.join("sym")).unwrap();
+        fs::write(target.join("sub/data"), b"data").unwrap();
+        fs::write(root.join("app/main.o"), b"object").unwrap();
+
+        let mtime = FileTime::from_unix_time(MTIME.0, MTIME.1);
+        for path in ["app/target/bin", "app/target/sub/data", "app/target/sub", "app/target", "app/main.o"] {
+            filetime::set_file_times(root.join(path), mtime, mtime).unwrap();
+        }
+        fs::set_permissions(target.join("sub"), fs::Permissions::from_mode(0o700)).unwrap();
+
+        ["app/target", "app/main.o"]
+            .iter()
+            .map(|name| (root.join(name), PathBuf::from(name)))
+            .collect()
+    }
+
+    fn mtime(path: &Path) -> (i64, u32) {
+        let mtime = FileTime::from_last_modification_time(&path.symlink_metadata().unwrap());
+        (mtime.unix_seconds(), mtime.nanoseconds())
+    }
+
+    /// Write `items` with `write`, then check the stored digests and that
+    /// extracting brings back the same contents, links, modes and times.
+    fn round_trip(dir: &Path, version_path: &Path, write: impl Fn(&[(PathBuf, PathBuf)]) -> io::Result<()>) {
+        let items = project(&dir.join("source"));
+        let names: Vec<PathBuf> = items.iter().map(|(_, name)| name.clone()).collect();
+        let originals: Vec<Digest> = items.iter().map(|(source, _)| checksum::item(source).unwrap()).collect();
+        write(&items).unwrap();
+
+        let stored = digests(version_path, &names).unwrap();
+        for (name, original) in names.iter().zip(&originals) {
+            assert_eq!(stored.get(name), Some(original), "stored digest of {}", name.display());
+        }
+
+        let restored = dir.join("restored");
+        let count = if store::is_manifest(version_path) {
+            store::extract(dir, version_path, |path| Some(restored.join(path))).unwrap()
+        } else {
+            archive::extract(version_path, |path| Some(restored.join(path))).unwrap()
+        };
+        assert_eq!(count, 7);
+
+        for (name, original) in names.iter().zip(&originals) {
+            assert_eq!(&checksum::item(&restored.join(name)).unwrap(), original, "restored {}", name.display());
+        }
+        let target = restored.join("app/target");
+        assert_eq!(fs::read_link(target.join("sym")).unwrap(), Path::new("bin"));
+        assert_eq!(target.join("bin").metadata().unwrap().ino(), target.join("bin-link").metadata().unwrap().ino());
+        assert_eq!(target.join("bin").metadata().unwrap().mode() & 0o7777, 0o751);
+        assert_eq!(target.join("sub").metadata().unwrap().mode() & 0o7777, 0o700);
+        for path in ["app/target/bin", "app/target/sub/data", "app/target/sub", "app/target", "app/main.o"] {
+            assert_eq!(mtime(&restored.join(path)), MTIME, "mtime of {}", path);
+        }
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn runs_in_the_same_second_get_their_own_version() {
+        let dir = scratch("same-second");
+        assert_eq!(unused_name(&dir, "20240101_000000"), "20240101_000000");
+        fs::write(dir.join(format!("20240101_000000.{}", archive::EXTENSION)), b"").unwrap();
+        assert_eq!(unused_name(&dir, "20240101_000000"), "20240101_000000_2");
+        fs::create_dir(dir.join("20240101_000000_2")).unwrap();
+        assert_eq!(unused_name(&dir, "20240101_000000"), "20240101_000000_3");
+        fs::create_dir(dir.join("20240101_000000_10")).unwrap();
+        fs::create_dir(dir.join("20240101_000000_02")).unwrap();
+
+        let names: Vec<String> = list(&dir).await.unwrap().into_iter().map(|version| version.name).collect();
+        assert_eq!(names, ["20240101_000000_10", "20240101_000000_2", "20240101_000000"]);
+
+        // A new archive never replaces an existing one
+        let items = project(&dir.join("source"));
+        let existing = dir.join(format!("20240101_000000.{}", archive::EXTENSION));
+        assert_eq!(archive::write(&existing, &items).unwrap_err().kind(), io::ErrorKind::AlreadyExists);
+        assert_eq!(fs::metadata(&existing).unwrap().len(), 0);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn archive_round_trip() {
+        let dir = scratch("archive");
+        let version_path = dir.join(format!("20240101_000000.{}", archive::EXTENSION));
+        round_trip(&dir, &version_path, |items| archive::write(&version_path, items));
+    }
+
+    #[test]
+    fn dedup_round_trip() {
+        let dir = scratch("dedup");
+        let version_path = dir.join(format!("20240101_000000.{}", store::MANIFEST_EXTENSION));
+        round_trip(&dir, &version_path, |items| store::write(&dir, &version_path, items));
+    }
+
+    #[test]
+    fn dedup_manifests_are_never_overwritten() {
+        let dir = scratch("dedup-existing");
+        let items = project(&dir.join("source"));
+        let version_path = dir.join(format!("20240101_000000.{}", store::MANIFEST_EXTENSION));
+        fs::write(&version_path, b"{}").unwrap();
+
+        let error = store::write(&dir, &version_path, &items).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::AlreadyExists);
+        assert_eq!(fs::read(&version_path).unwrap(), b"{}");
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn damaged_blobs_change_the_digest() {
+        let dir = scratch("damaged");
+        let items = project(&dir.join("source"));
+        let version_path = dir.join(format!("20240101_000000.{}", store::MANIFEST_EXTENSION));
+        store::write(&dir, &version_path, &items).unwrap();
+
+        let blob = dir.join("objects").join(&checksum::file(&items[1].0).unwrap()[..2]);
+        let blob = fs::read_dir(blob).unwrap().next().unwrap().unwrap().path();
+        fs::write(&blob, b"tampered").unwrap();
+
+        let stored = digests(&version_path, &[items[1].1.clone()]).unwrap();
+        assert_ne!(stored[&items[1].1], checksum::item(&items[1].0).unwrap());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn symlinked_directories_are_stored_as_links() {
+        let dir = scratch("symlinked");
+        let shared = dir.join("shared");
+        fs::create_dir_all(&shared).unwrap();
+        fs::write(shared.join("x"), b"shared").unwrap();
+        fs::create_dir_all(dir.join("source/app")).unwrap();
+        std::os::unix::fs::symlink(&shared, dir.join("source/app/dist")).unwrap();
+        let items = vec![(dir.join("source/app/dist"), PathBuf::from("app/dist"))];
+
+        let archive_path = dir.join(format!("20240101_000000.{}", archive::EXTENSION));
+        archive::write(&archive_path, &items).unwrap();
+        let names: Vec<PathBuf> = archive::list(&archive_path).unwrap().into_iter().map(|entry| entry.path).collect();
+        assert_eq!(names, [PathBuf::from("app/dist")]);
+
+        let manifest_path = dir.join(format!("20240101_000000.{}", store::MANIFEST_EXTENSION));
+        store::write(&dir, &manifest_path, &items).unwrap();
+        assert_eq!(store::list(&manifest_path).unwrap().len(), 1);
+
+        let copy = dir.join("copy/app/dist");
+        attributes::copy(&items[0].0, &copy).unwrap();
+        assert_eq!(fs::read_link(&copy).unwrap(), shared);
+
+        // Restoring the link again must not write through it
+        archive::extract(&archive_path, |path| Some(dir.join("copy").join(path))).unwrap();
+        store::extract(&dir, &manifest_path, |path| Some(dir.join("copy").join(path))).unwrap();
+        assert_eq!(fs::read_dir(&shared).unwrap().count(), 1);
+        assert_eq!(checksum::item(&copy).unwrap().size, 0);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,778 @@
+use colored::*;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+use tokio::io::AsyncReadExt;
+
+pub mod units;
+pub mod validate;
+
+use crate::ecosystems;
+
+pub use validate::{ConfigError, ValidationIssue, EXIT_CONFIG};
+
+/// Base name of the system and user configuration files.
+pub const CONFIG_FILE_STEM: &str = "clean.config";
+
+/// Base name of the per-repository configuration file, searched in and above the scanned directory.
+pub const PROJECT_CONFIG_FILE_STEM: &str = ".project-cleaner";
+
+/// Supported extensions, in the order they are looked up when several files exist side by side.
+pub const CONFIG_EXTENSIONS: [&str; 2] = ["toml", "json"];
+
+#[derive(Deserialize, Serialize, Clone, Default)]
+#[serde(default)]
+pub struct Config {
+    pub dir: String,
+    /// Named ecosystem presets whose patterns are added to `target`.
+    pub presets: Vec<String>,
+    pub target: Patterns,
+    pub exclude: Patterns,
+    /// Targets that are only cleaned next to a marker file, e.g. `target$` next to `Cargo.toml`.
+    pub marker_rules: Vec<MarkerRule>,
+    /// Only clean targets that have not been touched for a while.
+    pub age: AgeConfig,
+    /// Skip small targets and cap how much a single run cleans.
+    pub size: SizeConfig,
+    /// Leave git repositories alone while work in them is unfinished.
+    pub git: GitConfig,
+    pub backup: BackupConfig,
+    /// Files this configuration was merged from, lowest precedence first.
+    #[serde(skip)]
+    pub sources: Vec<PathBuf>,
+    /// Same shape as the serialized config, with the origin of every value
+    /// (a file path, `default` or a command-line flag) in place of the value.
+    #[serde(skip)]
+    pub provenance: Value,
+}
+
+#[derive(Deserialize, Serialize, Clone, Default)]
+#[serde(default)]
+pub struct Patterns {
+    pub files_patterns: Vec<String>,
+    pub dirs_patterns: Vec<String>,
+    /// Only meaningful while merging layers, see `MergeMode`.
+    #[serde(skip_serializing)]
+    pub merge: MergeMode,
+}
+
+/// A matched target whose path also matches `pattern` is only cleaned when its
+/// parent directory contains at least one of `markers` (`*.ext` globs allowed).
+#[derive(Deserialize, Serialize, Clone, Default)]
+#[serde(default)]
+pub struct MarkerRule {
+    pub pattern: String,
+    pub markers: Vec<String>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Default)]
+#[serde(default)]
+pub struct AgeConfig {
+    /// Minimum time since last use for every target, e.g. `30d`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_age: Option<String>,
+    /// Per-pattern minimum ages; the longest matching one wins over `min_age`.
+    pub rules: Vec<AgeRule>,
+    pub scope: AgeScope,
+    pub timestamp: AgeTimestamp,
+}
+
+#[derive(Deserialize, Serialize, Clone, Default)]
+#[serde(default)]
+pub struct AgeRule {
+    pub pattern: String,
+    pub min_age: String,
+}
+
+/// Which files decide how long ago a target was last used.
+#[derive(Deserialize, Serialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum AgeScope {
+    /// The matched file or everything inside the matched directory.
+    #[default]
+    Artifact,
+    /// The project owning the match: its parent directory, without the match itself.
+    Project,
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum AgeTimestamp {
+    #[default]
+    Mtime,
+    Atime,
+    /// The most recent of mtime and atime.
+    Newest,
+}
+
+#[derive(Deserialize, Serialize, Clone, Default)]
+#[serde(default)]
+pub struct SizeConfig {
+    /// Targets smaller than this are left alone, e.g. `1MiB`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_size: Option<String>,
+    /// Per-pattern minimum sizes; the largest matching one wins over `min_size`.
+    pub rules: Vec<SizeRule>,
+    /// Stop selecting targets once this much would be cleaned, e.g. `20GiB`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_total: Option<String>,
+    /// Which targets `max_total` picks first.
+    pub order: SelectionOrder,
+}
+
+#[derive(Deserialize, Serialize, Clone, Default)]
+#[serde(default)]
+pub struct SizeRule {
+    pub pattern: String,
+    pub min_size: String,
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SelectionOrder {
+    #[default]
+    Largest,
+    /// Least recently used first, judged like `age.timestamp`.
+    Oldest,
+}
+
+#[derive(Deserialize, Serialize, Clone, Default)]
+#[serde(default)]
+pub struct GitConfig {
+    /// Skip repositories with a rebase, merge, bisect, cherry-pick or revert in progress.
+    pub skip_in_progress: bool,
+    /// Skip matches modified after the last commit of their repository.
+    pub skip_newer_than_commit: bool,
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct BackupConfig {
+    pub enabled: bool,
+    pub mode: BackupMode,
+    pub dir: String,
+    pub versioning: bool,
+    pub format: BackupFormat,
+    /// Which versions `prune` keeps.
+    pub retention: RetentionConfig,
+    /// Compare every copy with its original by size and checksum before the
+    /// original is removed.
+    pub verify: bool,
+    /// Largest `dir` may grow to, e.g. `100GiB`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_size: Option<String>,
+    /// What a run does when its backup would not fit.
+    pub on_low_space: LowSpaceAction,
+}
+
+/// Checked before anything is backed up, against the free space of the
+/// backup filesystem and `max_size`.
+#[derive(Deserialize, Serialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum LowSpaceAction {
+    /// Stop before touching anything.
+    #[default]
+    Abort,
+    /// Remove the oldest versions until the backup fits.
+    Prune,
+    /// Delete the items without backing them up.
+    Delete,
+}
+
+/// Versions survive `prune` when any `keep_*` rule selects them (all of them
+/// when no rule is set) and they fit within `max_age` and `max_total_size`.
+/// The newest version is never pruned.
+#[derive(Deserialize, Serialize, Clone, Default)]
+#[serde(default)]
+pub struct RetentionConfig {
+    /// Keep the N most recent versions.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub keep_last: Option<usize>,
+    /// Keep the newest version of each of the last N days that have one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub keep_daily: Option<usize>,
+    /// Same per ISO week.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub keep_weekly: Option<usize>,
+    /// Same per calendar month.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub keep_monthly: Option<usize>,
+    /// Remove versions older than this, e.g. `90d`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_age: Option<String>,
+    /// Remove the oldest versions until the backups fit, e.g. `50GiB`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_total_size: Option<String>,
+}
+
+impl RetentionConfig {
+    pub fn is_empty(&self) -> bool {
+        self.keep_last.is_none()
+            && self.keep_daily.is_none()
+            && self.keep_weekly.is_none()
+            && self.keep_monthly.is_none()
+            && self.max_age.is_none()
+            && self.max_total_size.is_none()
+    }
+}
+
+/// Where cleaned items go when backups are enabled.
+#[derive(Deserialize, Serialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum BackupMode {
+    /// Copy items into `dir`, see `versioning`.
+    #[default]
+    Directory,
+    /// Move items to the freedesktop.org Trash, restorable from file managers.
+    Trash,
+}
+
+/// How a run is stored under `dir`.
+#[derive(Deserialize, Serialize, Clone, Copy, Default, PartialEq)]
+pub enum BackupFormat {
+    /// Items copied as they are, mirroring the project tree.
+    #[default]
+    #[serde(rename = "tree")]
+    Tree,
+    /// One zstd-compressed tarball per run, named after its timestamp.
+    #[serde(rename = "tar.zst")]
+    TarZst,
+    /// Files stored once by content hash under `objects/`, with a
+    /// `<timestamp>.dedup.json` manifest per run.
+    #[serde(rename = "dedup")]
+    Dedup,
+}
+
+impl Default for BackupConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            mode: BackupMode::default(),
+            dir: "~/.backup".to_string(),
+            versioning: false,
+            format: BackupFormat::default(),
+            retention: RetentionConfig::default(),
+            verify: false,
+            max_size: None,
+            on_low_space: LowSpaceAction::default(),
+        }
+    }
+}
+
+/// How a layer's lists combine with the ones from the layers below it.
+/// Set per section with `"merge": "replace"`.
+#[derive(Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum MergeMode {
+    #[default]
+    Append,
+    Replace,
+}
+
+/// One configuration file as written on disk. Every field is optional, so the
+/// file is kept as a JSON tree and only type-checked against `Config`.
+#[derive(Clone)]
+pub struct LoadedLayer {
+    pub path: PathBuf,
+    pub value: Value,
+}
+
+/// Values given on the command line that take precedence over the config file.
+#[derive(Default)]
+pub struct Overrides {
+    pub presets: Vec<String>,
+    pub min_age: Option<String>,
+    pub min_size: Option<String>,
+    pub max_total: Option<String>,
+    pub target_files: Vec<String>,
+    pub target_dirs: Vec<String>,
+    pub exclude_files: Vec<String>,
+    pub exclude_dirs: Vec<String>,
+    pub no_backup: bool,
+    pub backup_dir: Option<String>,
+}
+
+impl Overrides {
+    /// One layer per flag, so provenance can name the flag a value came from.
+    /// Pattern flags are appended to the configured lists.
+    fn layers(&self, dir: Option<&str>) -> Vec<(Value, &'static str)> {
+        let mut layers = Vec::new();
+        if let Some(dir) = dir {
+            layers.push((json!({ "dir": dir }), "--dir"));
+        }
+        if !self.presets.is_empty() {
+            layers.push((json!({ "presets": self.presets }), "--preset"));
+        }
+        for (flag, section, list, values) in [
+            ("--target-file", "target", "files_patterns", &self.target_files),
+            ("--target-dir", "target", "dirs_patterns", &self.target_dirs),
+            ("--exclude-file", "exclude", "files_patterns", &self.exclude_files),
+            ("--exclude-dir", "exclude", "dirs_patterns", &self.exclude_dirs),
+        ] {
+            if !values.is_empty() {
+                layers.push((json!({ section: { list: values } }), flag));
+            }
+        }
+        if let Some(min_age) = &self.min_age {
+            layers.push((json!({ "age": { "min_age": min_age } }), "--min-age"));
+        }
+        if let Some(min_size) = &self.min_size {
+            layers.push((json!({ "size": { "min_size": min_size } }), "--min-size"));
+        }
+        if let Some(max_total) = &self.max_total {
+            layers.push((json!({ "size": { "max_total": max_total } }), "--max-total"));
+        }
+        if let Some(dir) = &self.backup_dir {
+            layers.push((json!({ "backup": { "dir": dir } }), "--backup-dir"));
+        }
+        if self.no_backup {
+            layers.push((json!({ "backup": { "enabled": false } }), "--no-backup"));
+        }
+        layers
+    }
+}
+
+impl Config {
+    /// Run every validation rule, failing with all problems at once.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let issues = validate::validate(self);
+        if issues.is_empty() {
+            Ok(())
+        } else {
+            Err(ConfigError { file: None, issues })
+        }
+    }
+
+    /// Where the value at `pointer` (e.g. `/target/dirs_patterns/2`) came from.
+    pub fn origin(&self, pointer: &str) -> &str {
+        self.provenance
+            .pointer(pointer)
+            .and_then(Value::as_str)
+            .unwrap_or("default")
+    }
+
+    /// The effective configuration with every leaf replaced by `{ "value", "source" }`.
+    pub fn with_origins(&self) -> Result<Value, serde_json::Error> {
+        Ok(annotate(&serde_json::to_value(self)?, &self.provenance))
+    }
+}
+
+/// The chain of configuration files that make up the effective `Config`,
+/// lowest precedence first: system, user, then the project-local file.
+#[derive(Clone)]
+pub struct ConfigSources {
+    pub layers: Vec<LoadedLayer>,
+    verbose: bool,
+}
+
+impl ConfigSources {
+    /// Load the system and user layers, or only `explicit` when `--config` is given.
+    /// With `verbose` off nothing is printed, so stdout can carry machine-readable output.
+    pub async fn discover(explicit: Option<PathBuf>, verbose: bool) -> Result<Self, Box<dyn std::error::Error>> {
+        if verbose {
+            println!("\n{}", "📁 Loading configuration:".bright_blue().bold());
+        }
+
+        let mut layers = Vec::new();
+        if let Some(path) = explicit {
+            layers.push(load_layer(&path, verbose).await?);
+        } else {
+            let locations = [Some(system_config_dir()), user_config_dir()];
+            for dir in locations.into_iter().flatten() {
+                if let Some(path) = find_config_file(&dir, CONFIG_FILE_STEM) {
+                    layers.push(load_layer(&path, verbose).await?);
+                }
+            }
+        }
+
+        if layers.is_empty() && verbose {
+            println!("  {} {}", "→".bright_black(), "No system or user configuration found, using defaults".yellow());
+        }
+
+        Ok(Self { layers, verbose })
+    }
+
+    /// Resolve the effective configuration for one project directory.
+    ///
+    /// `dir` (from `--dir`) wins over the configured `dir`; the nearest
+    /// `.project-cleaner.{toml,json}` in or above that directory is merged
+    /// after the files, and command-line overrides go on top of everything.
+    pub async fn load(&self, dir: Option<&str>, overrides: &Overrides) -> Result<Config, Box<dyn std::error::Error>> {
+        let mut merged = serde_json::to_value(Config::default())?;
+        let mut provenance = origins_of(&merged, "default");
+
+        for layer in &self.layers {
+            merge(&mut merged, &mut provenance, &layer.value, &layer.path.display().to_string());
+        }
+
+        let project_dir = match dir {
+            Some(dir) => dir.to_string(),
+            None => merged["dir"].as_str().unwrap_or_default().to_string(),
+        };
+        if project_dir.is_empty() {
+            return Err(ConfigError {
+                file: None,
+                issues: vec![ValidationIssue::new(
+                    "$.dir",
+                    "no project directory configured",
+                    Some("set \"dir\" in a configuration file or pass --dir"),
+                )],
+            }
+            .into());
+        }
+
+        let mut sources: Vec<PathBuf> = self.layers.iter().map(|layer| layer.path.clone()).collect();
+        if let Some(path) = find_project_config(Path::new(&project_dir)) {
+            let mut project = load_layer(&path, self.verbose).await?;
+            // The project file is found through `dir`, so it cannot move it
+            if let Some(object) = project.value.as_object_mut() {
+                object.remove("dir");
+            }
+            merge(&mut merged, &mut provenance, &project.value, &path.display().to_string());
+            sources.push(path);
+        }
+
+        for (layer, flag) in overrides.layers(dir) {
+            merge(&mut merged, &mut provenance, &layer, flag);
+        }
+
+        expand_presets(&mut merged, &mut provenance);
+
+        let mut config: Config = serde_json::from_value(merged)?;
+        config.dir = shellexpand::tilde(&config.dir).into_owned();
+        // Manifests and trash info record where items came from, which must
+        // not depend on the directory the cleaner ran in
+        let dir = Path::new(&config.dir);
+        if let Ok(dir) = dir.canonicalize().or_else(|_| std::path::absolute(dir)) {
+            config.dir = dir.to_string_lossy().into_owned();
+        }
+        config.backup.dir = shellexpand::tilde(&config.backup.dir).into_owned();
+        config.sources = sources;
+        config.provenance = provenance;
+        Ok(config)
+    }
+}
+
+/// Deep-merge `layer` into `base`: objects are merged key by key, lists are
+/// appended (or replaced when the enclosing section says `"merge": "replace"`)
+/// and everything else is overwritten. `origins` mirrors `base` and records
+/// `source` for every value taken from `layer`.
+fn merge(base: &mut Value, origins: &mut Value, layer: &Value, source: &str) {
+    let Some(layer) = layer.as_object() else {
+        return;
+    };
+    let replace_lists = layer.get("merge").and_then(Value::as_str) == Some("replace");
+
+    if !base.is_object() {
+        *base = json!({});
+    }
+    if !origins.is_object() {
+        *origins = json!({});
+    }
+
+    for (key, incoming) in layer {
+        if key == "merge" {
+            continue;
+        }
+        let current = &mut base[key.as_str()];
+        let current_origin = &mut origins[key.as_str()];
+
+        match (current, incoming) {
+            (current @ Value::Object(_), Value::Object(_)) => {
+                merge(current, current_origin, incoming, source);
+            }
+            (Value::Array(current), Value::Array(incoming)) => {
+                if replace_lists {
+                    current.clear();
+                    *current_origin = json!([]);
+                }
+                if !current_origin.is_array() {
+                    *current_origin = json!([]);
+                }
+                current.extend(incoming.iter().cloned());
+                if let Value::Array(origin_list) = current_origin {
+                    origin_list.extend(incoming.iter().map(|item| origins_of(item, source)));
+                }
+            }
+            (current, incoming) => {
+                *current = incoming.clone();
+                *current_origin = origins_of(incoming, source);
+            }
+        }
+    }
+}
+
+/// Put the patterns of every preset named in `presets` in front of the
+/// custom target patterns, skipping duplicates, and add the marker rules of
+/// the preset. Unknown names are left for validation to report.
+fn expand_presets(merged: &mut Value, provenance: &mut Value) {
+    let presets: Vec<(String, String)> = merged["presets"]
+        .as_array()
+        .map(|names| {
+            names
+                .iter()
+                .enumerate()
+                .filter_map(|(index, name)| {
+                    let origin = provenance["presets"][index].as_str().unwrap_or("default");
+                    Some((name.as_str()?.to_string(), origin.to_string()))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    if presets.is_empty() {
+        return;
+    }
+
+    for list in ["files_patterns", "dirs_patterns"] {
+        let mut patterns: Vec<Value> = Vec::new();
+        let mut origins: Vec<Value> = Vec::new();
+
+        for (name, origin) in &presets {
+            let Some(ecosystem) = ecosystems::find(name) else {
+                continue;
+            };
+            for pattern in ecosystem.patterns(list) {
+                let pattern = json!(pattern);
+                if !patterns.contains(&pattern) {
+                    patterns.push(pattern);
+                    origins.push(json!(format!("preset {} ({})", name, origin)));
+                }
+            }
+        }
+
+        let custom = merged["target"][list].as_array().cloned().unwrap_or_default();
+        for (index, pattern) in custom.into_iter().enumerate() {
+            if !patterns.contains(&pattern) {
+                patterns.push(pattern);
+                origins.push(provenance["target"][list][index].clone());
+            }
+        }
+
+        merged["target"][list] = Value::Array(patterns);
+        provenance["target"][list] = Value::Array(origins);
+    }
+
+    for (name, origin) in &presets {
+        let Some(ecosystem) = ecosystems::find(name) else {
+            continue;
+        };
+        let source = format!("preset {} ({})", name, origin);
+        for pattern in ecosystem.marker_dirs {
+            let rule = json!({ "pattern": pattern, "markers": ecosystem.markers });
+            merge(merged, provenance, &json!({ "marker_rules": [rule] }), &source);
+        }
+    }
+}
+
+/// A provenance tree for `value` where every leaf comes from `source`.
+fn origins_of(value: &Value, source: &str) -> Value {
+    match value {
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .map(|(key, value)| (key.clone(), origins_of(value, source)))
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(items.iter().map(|item| origins_of(item, source)).collect()),
+        _ => json!(source),
+    }
+}
+
+/// Pair every leaf of `value` with the matching entry of `origins`.
+fn annotate(value: &Value, origins: &Value) -> Value {
+    match value {
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .map(|(key, value)| (key.clone(), annotate(value, &origins[key.as_str()])))
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(
+            items
+                .iter()
+                .enumerate()
+                .map(|(index, item)| annotate(item, origins.get(index).unwrap_or(&Value::Null)))
+                .collect(),
+        ),
+        leaf => json!({
+            "value": leaf,
+            "source": origins.as_str().unwrap_or("default"),
+        }),
+    }
+}
+
+pub fn system_config_dir() -> PathBuf {
+    if cfg!(debug_assertions) {
+        // Debug mode - look in current directory
+        PathBuf::from(".")
+    } else {
+        // Release mode - look in /etc/project-cleaner/
+        PathBuf::from("/etc/project-cleaner")
+    }
+}
+
+/// `$XDG_CONFIG_HOME/project-cleaner`, falling back to `~/.config/project-cleaner`.
+pub fn user_config_dir() -> Option<PathBuf> {
+    let config_home = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|value| !value.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+
+    Some(config_home.join("project-cleaner"))
+}
+
+/// First `<stem>.<ext>` that exists in `dir`, trying extensions in `CONFIG_EXTENSIONS` order.
+fn find_config_file(dir: &Path, stem: &str) -> Option<PathBuf> {
+    CONFIG_EXTENSIONS
+        .iter()
+        .map(|ext| dir.join(format!("{}.{}", stem, ext)))
+        .find(|path| path.is_file())
+}
+
+/// Nearest `.project-cleaner.{toml,json}` in `dir` or one of its ancestors.
+fn find_project_config(dir: &Path) -> Option<PathBuf> {
+    let dir = PathBuf::from(shellexpand::tilde(&dir.to_string_lossy()).into_owned());
+    dir.ancestors()
+        .find_map(|ancestor| find_config_file(ancestor, PROJECT_CONFIG_FILE_STEM))
+}
+
+async fn load_layer(config_path: &Path, verbose: bool) -> Result<LoadedLayer, Box<dyn std::error::Error>> {
+    if verbose {
+        println!("  {} Path: {}", "→".bright_black(), config_path.display().to_string().bright_white());
+    }
+
+    let mut file = tokio::fs::File::open(config_path).await.map_err(|e| ConfigError {
+        file: Some(config_path.to_path_buf()),
+        issues: vec![ValidationIssue::new(
+            "$",
+            format!("failed to open config file: {}", e),
+            Some("check the path passed to --config and its permissions"),
+        )],
+    })?;
+
+    let mut contents = String::new();
+    file.read_to_string(&mut contents).await?;
+
+    // The format is picked by extension; anything that is not .toml is read as JSON
+    let value: Value = match config_path.extension().and_then(|ext| ext.to_str()) {
+        Some("toml") => toml::from_str(&contents).map_err(|e| ConfigError {
+            file: Some(config_path.to_path_buf()),
+            issues: vec![ValidationIssue::new("$", e.to_string().trim_end(), None)],
+        })?,
+        _ => {
+            let mut deserializer = serde_json::Deserializer::from_str(&contents);
+            serde_path_to_error::deserialize(&mut deserializer)
+                .map_err(|e| parse_error(config_path, e))?
+        }
+    };
+
+    // Type-check the layer on its own so errors point at the file they are in
+    serde_path_to_error::deserialize::<_, Config>(&value).map_err(|e| parse_error(config_path, e))?;
+
+    Ok(LoadedLayer {
+        path: config_path.to_path_buf(),
+        value,
+    })
+}
+
+fn parse_error<E: std::fmt::Display>(config_path: &Path, error: serde_path_to_error::Error<E>) -> ConfigError {
+    let path = match error.path().to_string() {
+        root if root == "." => "$".to_string(),
+        field => format!("$.{}", field),
+    };
+    let message = error.inner().to_string().trim_end().to_string();
+    let suggestion = if message.contains("escape") {
+        Some("backslashes must be doubled in JSON strings, e.g. \"\\\\.pyc$\"")
+    } else {
+        None
+    };
+
+    ConfigError {
+        file: Some(config_path.to_path_buf()),
+        issues: vec![ValidationIssue::new(path, message, suggestion)],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Merge `layers` (source, value) into an empty configuration.
+    fn layered(layers: &[(&str, Value)]) -> (Value, Value) {
+        let (mut merged, mut provenance) = (json!({}), json!({}));
+        for (source, layer) in layers {
+            merge(&mut merged, &mut provenance, layer, source);
+        }
+        (merged, provenance)
+    }
+
+    #[test]
+    fn later_layers_override_scalars() {
+        let (merged, provenance) = layered(&[
+            ("system", json!({ "dir": "/a", "backup": { "enabled": true, "dir": "/b" } })),
+            ("user", json!({ "backup": { "dir": "/c" } })),
+        ]);
+        assert_eq!(merged, json!({ "dir": "/a", "backup": { "enabled": true, "dir": "/c" } }));
+        assert_eq!(provenance, json!({ "dir": "system", "backup": { "enabled": "system", "dir": "user" } }));
+    }
+
+    #[test]
+    fn lists_are_appended() {
+        let (merged, provenance) = layered(&[
+            ("system", json!({ "target": { "dirs_patterns": ["target$"] } })),
+            ("user", json!({ "target": { "dirs_patterns": ["dist$"] } })),
+        ]);
+        assert_eq!(merged["target"]["dirs_patterns"], json!(["target$", "dist$"]));
+        assert_eq!(provenance["target"]["dirs_patterns"], json!(["system", "user"]));
+    }
+
+    #[test]
+    fn merge_replace_starts_the_lists_over() {
+        let (merged, provenance) = layered(&[
+            ("system", json!({ "target": { "dirs_patterns": ["target$"], "files_patterns": ["\\.o$"] } })),
+            ("user", json!({ "target": { "merge": "replace", "dirs_patterns": ["dist$"] } })),
+        ]);
+        assert_eq!(merged["target"]["dirs_patterns"], json!(["dist$"]));
+        assert_eq!(provenance["target"]["dirs_patterns"], json!(["user"]));
+        // Lists the layer does not mention are left alone, and `merge` itself is not kept
+        assert_eq!(merged["target"]["files_patterns"], json!(["\\.o$"]));
+        assert!(merged["target"].get("merge").is_none());
+    }
+
+    #[test]
+    fn replace_only_applies_to_its_own_section() {
+        let (merged, _) = layered(&[
+            ("system", json!({ "target": { "dirs_patterns": ["target$"] }, "exclude": { "dirs_patterns": ["\\.git$"] } })),
+            ("user", json!({ "target": { "merge": "replace", "dirs_patterns": [] }, "exclude": { "dirs_patterns": ["test$"] } })),
+        ]);
+        assert_eq!(merged["target"]["dirs_patterns"], json!([]));
+        assert_eq!(merged["exclude"]["dirs_patterns"], json!(["\\.git$", "test$"]));
+    }
+
+    #[test]
+    fn provenance_follows_objects_in_lists() {
+        let (_, provenance) = layered(&[("--flag", json!({ "marker_rules": [{ "pattern": "build$", "markers": ["x"] }] }))]);
+        assert_eq!(provenance["marker_rules"], json!([{ "pattern": "--flag", "markers": ["--flag"] }]));
+    }
+
+    #[test]
+    fn presets_come_before_custom_patterns_without_duplicates() {
+        let (mut merged, mut provenance) = layered(&[(
+            "user",
+            json!({ "presets": ["rust"], "target": { "dirs_patterns": ["target$", "out$"], "files_patterns": [] } }),
+        )]);
+        expand_presets(&mut merged, &mut provenance);
+
+        assert_eq!(merged["target"]["dirs_patterns"], json!(["target$", "out$"]));
+        assert_eq!(provenance["target"]["dirs_patterns"], json!(["preset rust (user)", "user"]));
+        assert_eq!(merged["marker_rules"], json!([{ "pattern": "target$", "markers": ["Cargo.toml"] }]));
+        assert_eq!(provenance["marker_rules"][0]["pattern"], json!("preset rust (user)"));
+    }
+
+    #[test]
+    fn unknown_presets_are_left_for_validation() {
+        let (mut merged, mut provenance) = layered(&[("user", json!({ "presets": ["nope"], "target": { "dirs_patterns": ["out$"] } }))]);
+        expand_presets(&mut merged, &mut provenance);
+        assert_eq!(merged["target"]["dirs_patterns"], json!(["out$"]));
+        assert_eq!(merged["presets"], json!(["nope"]));
+    }
+}
@@ -0,0 +1,166 @@
+use std::time::Duration;
+
+const DURATION_UNITS: [(&str, u64); 5] = [
+    ("w", 7 * 24 * 60 * 60),
+    ("d", 24 * 60 * 60),
+    ("h", 60 * 60),
+    ("m", 60),
+    ("s", 1),
+];
+
+/// Parse a duration such as `30d`, `12h` or `2w`. Units: s, m, h, d, w.
+pub fn parse_duration(value: &str) -> Result<Duration, String> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .ok_or_else(|| format!("missing unit in \"{}\"", value))?;
+    let (amount, unit) = value.split_at(split);
+
+    let amount: u64 = amount
+        .parse()
+        .map_err(|_| format!("missing number in \"{}\"", value))?;
+    let seconds = DURATION_UNITS
+        .iter()
+        .find(|(name, _)| *name == unit.trim())
+        .map(|(_, seconds)| *seconds)
+        .ok_or_else(|| format!("unknown unit \"{}\" in \"{}\"", unit.trim(), value))?;
+
+    amount
+        .checked_mul(seconds)
+        .map(Duration::from_secs)
+        .ok_or_else(|| format!("duration too large in \"{}\"", value))
+}
+
+/// Render a duration with its largest whole unit, e.g. `3d` or `5h`.
+pub fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    for (name, unit) in DURATION_UNITS {
+        if seconds >= unit && name != "w" {
+            return format!("{}{}", seconds / unit, name);
+        }
+    }
+    format!("{}s", seconds)
+}
+
+const SIZE_UNITS: [(&str, u64); 13] = [
+    ("B", 1),
+    ("K", 1 << 10),
+    ("KiB", 1 << 10),
+    ("KB", 1_000),
+    ("M", 1 << 20),
+    ("MiB", 1 << 20),
+    ("MB", 1_000_000),
+    ("G", 1 << 30),
+    ("GiB", 1 << 30),
+    ("GB", 1_000_000_000),
+    ("T", 1 << 40),
+    ("TiB", 1 << 40),
+    ("TB", 1_000_000_000_000),
+];
+
+/// Parse a size such as `4KiB`, `1.5 GiB` or `500MB` into bytes.
+/// A bare number is taken as bytes.
+pub fn parse_size(value: &str) -> Result<u64, String> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(value.len());
+    let (amount, unit) = value.split_at(split);
+
+    let amount: f64 = amount
+        .parse()
+        .map_err(|_| format!("missing number in \"{}\"", value))?;
+    let unit = unit.trim();
+    let multiplier = if unit.is_empty() {
+        1
+    } else {
+        // Exact spelling first, so `KB` and `KiB` keep their meaning, then any case
+        SIZE_UNITS
+            .iter()
+            .find(|(name, _)| *name == unit)
+            .or_else(|| SIZE_UNITS.iter().find(|(name, _)| name.eq_ignore_ascii_case(unit)))
+            .map(|(_, multiplier)| *multiplier)
+            .ok_or_else(|| format!("unknown unit \"{}\" in \"{}\"", unit, value))?
+    };
+
+    let bytes = amount * multiplier as f64;
+    // `as` would quietly saturate
+    if bytes >= u64::MAX as f64 {
+        return Err(format!("size too large in \"{}\"", value));
+    }
+    Ok(bytes as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_durations() {
+        assert_eq!(parse_duration("30d"), Ok(Duration::from_secs(30 * 24 * 60 * 60)));
+        assert_eq!(parse_duration(" 2w "), Ok(Duration::from_secs(2 * 7 * 24 * 60 * 60)));
+        assert_eq!(parse_duration("12 h"), Ok(Duration::from_secs(12 * 60 * 60)));
+        assert_eq!(parse_duration("0s"), Ok(Duration::ZERO));
+    }
+
+    #[test]
+    fn rejects_malformed_durations() {
+        assert!(parse_duration("30").unwrap_err().contains("missing unit"));
+        assert!(parse_duration("d").unwrap_err().contains("missing number"));
+        assert!(parse_duration("").unwrap_err().contains("missing unit"));
+        assert!(parse_duration("3y").unwrap_err().contains("unknown unit"));
+        assert!(parse_duration("-3d").unwrap_err().contains("missing number"));
+    }
+
+    #[test]
+    fn duration_bounds() {
+        assert_eq!(parse_duration("18446744073709551615s"), Ok(Duration::from_secs(u64::MAX)));
+        assert!(parse_duration("18446744073709551616s").unwrap_err().contains("missing number"));
+        assert!(parse_duration("18446744073709551615m").unwrap_err().contains("too large"));
+        assert!(parse_duration("9999999999999999999d").unwrap_err().contains("too large"));
+    }
+
+    #[test]
+    fn formats_durations_with_their_largest_unit() {
+        assert_eq!(format_duration(Duration::from_secs(3 * 24 * 60 * 60)), "3d");
+        // Weeks are shown as days
+        assert_eq!(format_duration(Duration::from_secs(14 * 24 * 60 * 60)), "14d");
+        assert_eq!(format_duration(Duration::from_secs(5 * 60 * 60 + 59)), "5h");
+        assert_eq!(format_duration(Duration::from_secs(42)), "42s");
+        assert_eq!(format_duration(Duration::ZERO), "0s");
+    }
+
+    #[test]
+    fn parses_sizes() {
+        assert_eq!(parse_size("1024"), Ok(1024));
+        assert_eq!(parse_size("4KiB"), Ok(4096));
+        assert_eq!(parse_size("4K"), Ok(4096));
+        assert_eq!(parse_size("500MB"), Ok(500_000_000));
+        assert_eq!(parse_size("1.5 GiB"), Ok(3 << 29));
+        assert_eq!(parse_size("2TB"), Ok(2_000_000_000_000));
+    }
+
+    #[test]
+    fn size_units_keep_their_meaning_in_any_case() {
+        assert_eq!(parse_size("1KB"), Ok(1_000));
+        assert_eq!(parse_size("1kib"), Ok(1 << 10));
+        assert_eq!(parse_size("1gb"), Ok(1_000_000_000));
+        assert_eq!(parse_size("1m"), Ok(1 << 20));
+    }
+
+    #[test]
+    fn rejects_malformed_sizes() {
+        assert!(parse_size("").unwrap_err().contains("missing number"));
+        assert!(parse_size("GiB").unwrap_err().contains("missing number"));
+        assert!(parse_size("1.2.3MB").unwrap_err().contains("missing number"));
+        assert!(parse_size("10XB").unwrap_err().contains("unknown unit"));
+    }
+
+    #[test]
+    fn size_bounds() {
+        assert_eq!(parse_size("0"), Ok(0));
+        assert_eq!(parse_size("16000000TB"), Ok(16_000_000_000_000_000_000));
+        assert!(parse_size("20000000TB").unwrap_err().contains("too large"));
+        assert!(parse_size("99999999999999999999999").unwrap_err().contains("too large"));
+    }
+}
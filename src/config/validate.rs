@@ -0,0 +1,329 @@
+use colored::*;
+use regex::Regex;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use super::{units, BackupFormat, BackupMode, Config, LowSpaceAction, Patterns};
+use crate::ecosystems;
+
+/// Exit status used when the configuration cannot be loaded or is invalid (`EX_CONFIG` from sysexits.h).
+pub const EXIT_CONFIG: i32 = 78;
+
+const DURATION_HINT: &str = "use a number followed by s, m, h, d or w, e.g. \"30d\"";
+
+const SIZE_HINT: &str = "use a number followed by B, KiB, MiB, GiB or TiB (KB, MB... for powers of 1000), e.g. \"20GiB\"";
+
+const REGEX_HINT: &str = "patterns are regular expressions: escape special characters, e.g. \"\\\\.pyc$\" for a literal dot";
+
+/// A single problem found in the configuration.
+#[derive(Debug, Clone)]
+pub struct ValidationIssue {
+    /// Where the offending value lives, e.g. `$.target.dirs_patterns[2]`.
+    pub path: String,
+    pub message: String,
+    pub suggestion: Option<String>,
+}
+
+/// The configuration could not be used; carries every issue found, not just the first one.
+#[derive(Debug)]
+pub struct ConfigError {
+    pub file: Option<PathBuf>,
+    pub issues: Vec<ValidationIssue>,
+}
+
+impl ValidationIssue {
+    pub(super) fn new(path: impl Into<String>, message: impl Into<String>, suggestion: Option<&str>) -> Self {
+        Self {
+            path: path.into(),
+            message: message.into(),
+            suggestion: suggestion.map(str::to_string),
+        }
+    }
+}
+
+impl ConfigError {
+    pub fn display_report(&self) {
+        match &self.file {
+            Some(file) => eprintln!(
+                "\n{} {}",
+                "❌ Invalid configuration in".red().bold(),
+                file.display().to_string().bright_white()
+            ),
+            None => eprintln!("\n{}", "❌ Invalid configuration:".red().bold()),
+        }
+
+        for issue in &self.issues {
+            eprintln!("  {} {}: {}", "→".bright_black(), issue.path.yellow(), issue.message);
+            if let Some(suggestion) = &issue.suggestion {
+                eprintln!("    {} {}", "💡".bold(), suggestion.bright_black());
+            }
+        }
+
+        eprintln!(
+            "\n{}",
+            format!("{} problem(s) found.", self.issues.len()).red()
+        );
+    }
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let issues: Vec<String> = self
+            .issues
+            .iter()
+            .map(|issue| format!("{}: {}", issue.path, issue.message))
+            .collect();
+        write!(f, "Invalid configuration: {}", issues.join("; "))
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// Check a resolved configuration and collect every problem found.
+pub fn validate(config: &Config) -> Vec<ValidationIssue> {
+    let mut issues = Vec::new();
+
+    for (index, name) in config.presets.iter().enumerate() {
+        if ecosystems::find(name).is_none() {
+            issues.push(ValidationIssue::new(
+                format!("$.presets[{}]", index),
+                format!("unknown preset \"{}\"", name),
+                Some(&format!("available presets: {}", ecosystems::names().join(", "))),
+            ));
+        }
+    }
+
+    check_patterns(&config.target, "target", true, &mut issues);
+    check_patterns(&config.exclude, "exclude", false, &mut issues);
+
+    for (index, rule) in config.marker_rules.iter().enumerate() {
+        let path = format!("$.marker_rules[{}]", index);
+        if let Err(e) = Regex::new(&rule.pattern) {
+            issues.push(ValidationIssue::new(
+                format!("{}.pattern", path),
+                format!("invalid regex \"{}\": {}", rule.pattern, regex_reason(&e)),
+                Some(REGEX_HINT),
+            ));
+        }
+        if rule.markers.is_empty() {
+            issues.push(ValidationIssue::new(
+                format!("{}.markers", path),
+                "no marker files listed, matching targets would never be cleaned",
+                Some("list the files that identify the project, e.g. [\"Cargo.toml\"]"),
+            ));
+        }
+    }
+
+    if let Some(min_age) = &config.age.min_age {
+        if let Err(e) = units::parse_duration(min_age) {
+            issues.push(ValidationIssue::new("$.age.min_age", format!("invalid duration: {}", e), Some(DURATION_HINT)));
+        }
+    }
+    for (index, rule) in config.age.rules.iter().enumerate() {
+        let path = format!("$.age.rules[{}]", index);
+        if let Err(e) = Regex::new(&rule.pattern) {
+            issues.push(ValidationIssue::new(
+                format!("{}.pattern", path),
+                format!("invalid regex \"{}\": {}", rule.pattern, regex_reason(&e)),
+                Some(REGEX_HINT),
+            ));
+        }
+        if let Err(e) = units::parse_duration(&rule.min_age) {
+            issues.push(ValidationIssue::new(format!("{}.min_age", path), format!("invalid duration: {}", e), Some(DURATION_HINT)));
+        }
+    }
+
+    for (path, value) in [
+        ("$.size.min_size", &config.size.min_size),
+        ("$.size.max_total", &config.size.max_total),
+    ] {
+        if let Some(Err(e)) = value.as_deref().map(units::parse_size) {
+            issues.push(ValidationIssue::new(path, format!("invalid size: {}", e), Some(SIZE_HINT)));
+        }
+    }
+    for (index, rule) in config.size.rules.iter().enumerate() {
+        let path = format!("$.size.rules[{}]", index);
+        if let Err(e) = Regex::new(&rule.pattern) {
+            issues.push(ValidationIssue::new(
+                format!("{}.pattern", path),
+                format!("invalid regex \"{}\": {}", rule.pattern, regex_reason(&e)),
+                Some(REGEX_HINT),
+            ));
+        }
+        if let Err(e) = units::parse_size(&rule.min_size) {
+            issues.push(ValidationIssue::new(format!("{}.min_size", path), format!("invalid size: {}", e), Some(SIZE_HINT)));
+        }
+    }
+
+    if config.target.files_patterns.is_empty() && config.target.dirs_patterns.is_empty() {
+        issues.push(ValidationIssue::new(
+            "$.target",
+            "both files_patterns and dirs_patterns are empty, nothing would be cleaned",
+            Some("add at least one pattern, e.g. \"target$\" to dirs_patterns"),
+        ));
+    }
+
+    let project_dir = absolute(&config.dir);
+    if config.dir.trim().is_empty() {
+        issues.push(ValidationIssue::new(
+            "$.dir",
+            "project directory is empty",
+            Some("set \"dir\" to the directory containing your projects or pass --dir"),
+        ));
+    } else if !project_dir.exists() {
+        issues.push(ValidationIssue::new(
+            "$.dir",
+            format!("project directory {} does not exist", project_dir.display()),
+            Some("check the path or pass --dir"),
+        ));
+    } else if !project_dir.is_dir() {
+        issues.push(ValidationIssue::new(
+            "$.dir",
+            format!("{} is not a directory", project_dir.display()),
+            Some("point \"dir\" at a directory, not a file"),
+        ));
+    }
+
+    if config.backup.enabled && config.backup.mode == BackupMode::Directory {
+        let backup_dir = absolute(&config.backup.dir);
+        if config.backup.dir.trim().is_empty() {
+            issues.push(ValidationIssue::new(
+                "$.backup.dir",
+                "backup directory is empty",
+                Some("set \"backup.dir\", e.g. \"~/.backup\", or disable backups"),
+            ));
+        } else if backup_dir.exists() && !backup_dir.is_dir() {
+            issues.push(ValidationIssue::new(
+                "$.backup.dir",
+                format!("{} exists and is not a directory", backup_dir.display()),
+                Some("choose a different backup directory"),
+            ));
+        } else if backup_dir.starts_with(&project_dir) {
+            issues.push(ValidationIssue::new(
+                "$.backup.dir",
+                format!(
+                    "backup directory {} is inside the project directory {}",
+                    backup_dir.display(),
+                    project_dir.display()
+                ),
+                Some("move backups outside of \"dir\", otherwise they are scanned and cleaned on the next run"),
+            ));
+        } else if project_dir.starts_with(&backup_dir) {
+            issues.push(ValidationIssue::new(
+                "$.backup.dir",
+                format!(
+                    "project directory {} is inside the backup directory {}",
+                    project_dir.display(),
+                    backup_dir.display()
+                ),
+                Some("use a dedicated directory for backups"),
+            ));
+        }
+
+        if let Some(Err(e)) = config.backup.max_size.as_deref().map(units::parse_size) {
+            issues.push(ValidationIssue::new("$.backup.max_size", format!("invalid size: {}", e), Some(SIZE_HINT)));
+        }
+        if config.backup.on_low_space == LowSpaceAction::Prune && !config.backup.versioning {
+            issues.push(ValidationIssue::new(
+                "$.backup.on_low_space",
+                "\"prune\" removes old backup versions, but versioning is disabled",
+                Some("set \"backup.versioning\" to true, or use \"abort\" or \"delete\""),
+            ));
+        }
+
+        if config.backup.format != BackupFormat::Tree && !config.backup.versioning {
+            issues.push(ValidationIssue::new(
+                "$.backup.format",
+                "\"tar.zst\" and \"dedup\" backups are written as one file per run and need versioning",
+                Some("set \"backup.versioning\" to true, or use the \"tree\" format"),
+            ));
+        }
+    }
+
+    let retention = &config.backup.retention;
+    if let Some(Err(e)) = retention.max_age.as_deref().map(units::parse_duration) {
+        issues.push(ValidationIssue::new("$.backup.retention.max_age", format!("invalid duration: {}", e), Some(DURATION_HINT)));
+    }
+    if let Some(Err(e)) = retention.max_total_size.as_deref().map(units::parse_size) {
+        issues.push(ValidationIssue::new("$.backup.retention.max_total_size", format!("invalid size: {}", e), Some(SIZE_HINT)));
+    }
+    if !retention.is_empty() && (!config.backup.versioning || config.backup.mode == BackupMode::Trash) {
+        issues.push(ValidationIssue::new(
+            "$.backup.retention",
+            "retention only applies to versioned backups in a backup directory",
+            Some("set \"backup.versioning\" to true, or remove \"backup.retention\""),
+        ));
+    }
+
+    if config.backup.enabled && config.backup.mode == BackupMode::Trash && config.backup.format != BackupFormat::Tree {
+        issues.push(ValidationIssue::new(
+            "$.backup.format",
+            "the trash only holds plain files and directories",
+            Some("use the \"tree\" format with the \"trash\" mode"),
+        ));
+    }
+
+    issues
+}
+
+fn check_patterns(patterns: &Patterns, section: &str, is_target: bool, issues: &mut Vec<ValidationIssue>) {
+    for (list, values) in [
+        ("files_patterns", &patterns.files_patterns),
+        ("dirs_patterns", &patterns.dirs_patterns),
+    ] {
+        for (index, pattern) in values.iter().enumerate() {
+            let path = format!("$.{}.{}[{}]", section, list, index);
+
+            if pattern.is_empty() {
+                let message = if is_target {
+                    "empty pattern matches every path"
+                } else {
+                    "empty pattern excludes every path"
+                };
+                issues.push(ValidationIssue::new(path, message, Some("remove the empty string from the list")));
+                continue;
+            }
+
+            if let Err(e) = Regex::new(pattern) {
+                issues.push(ValidationIssue::new(
+                    path,
+                    format!("invalid regex \"{}\": {}", pattern, regex_reason(&e)),
+                    Some(REGEX_HINT),
+                ));
+            }
+        }
+    }
+}
+
+/// The regex error spans several lines; keep only the part explaining what went wrong.
+fn regex_reason(error: &regex::Error) -> String {
+    let reason = error.to_string();
+    let reason = reason.lines().last().unwrap_or("invalid regex").trim();
+    reason.trim_start_matches("error: ").to_string()
+}
+
+/// Best-effort absolute form of a configured path: tilde-expanded, made
+/// absolute against the current directory and canonicalized when it exists.
+fn absolute(path: &str) -> PathBuf {
+    let expanded = PathBuf::from(shellexpand::tilde(path).into_owned());
+    let absolute = if expanded.is_relative() {
+        std::env::current_dir()
+            .map(|cwd| cwd.join(&expanded))
+            .unwrap_or(expanded)
+    } else {
+        expanded
+    };
+
+    canonicalize_existing(&absolute)
+}
+
+/// Canonicalize the longest existing prefix of `path` so symlinked parents compare correctly.
+fn canonicalize_existing(path: &Path) -> PathBuf {
+    for ancestor in path.ancestors() {
+        if let Ok(canonical) = ancestor.canonicalize() {
+            let rest = path.strip_prefix(ancestor).unwrap_or(Path::new(""));
+            return canonical.join(rest);
+        }
+    }
+    path.to_path_buf()
+}
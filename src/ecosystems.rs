@@ -0,0 +1,111 @@
+/// A build ecosystem: the files that identify a project using it and the
+/// patterns matching the artifacts its tooling produces. Each one is also
+/// available as a preset, referenced by `name` from the `presets` config list.
+pub struct Ecosystem {
+    pub name: &'static str,
+    /// File names marking a project root; `*.ext` matches by extension.
+    pub markers: &'static [&'static str],
+    pub files_patterns: &'static [&'static str],
+    pub dirs_patterns: &'static [&'static str],
+    /// Directory patterns only cleaned when one of `markers` sits next to the match.
+    pub marker_dirs: &'static [&'static str],
+}
+
+pub const ECOSYSTEMS: &[Ecosystem] = &[
+    Ecosystem {
+        name: "rust",
+        markers: &["Cargo.toml"],
+        files_patterns: &[],
+        dirs_patterns: &["target$"],
+        marker_dirs: &["target$"],
+    },
+    Ecosystem {
+        name: "node",
+        markers: &["package.json"],
+        files_patterns: &[],
+        dirs_patterns: &["node_modules$", "dist$", "\\.next$", "\\.nuxt$", "\\.parcel-cache$"],
+        marker_dirs: &["node_modules$", "dist$", "\\.next$", "\\.nuxt$", "\\.parcel-cache$"],
+    },
+    Ecosystem {
+        name: "python",
+        markers: &["pyproject.toml", "setup.py", "setup.cfg"],
+        files_patterns: &["\\.pyc$", "\\.pyo$"],
+        dirs_patterns: &[
+            "__pycache__$",
+            "\\.pytest_cache$",
+            "\\.mypy_cache$",
+            "\\.tox$",
+            "\\.egg-info$",
+            "build$",
+            "dist$",
+        ],
+        marker_dirs: &["build$", "dist$"],
+    },
+    Ecosystem {
+        name: "java-gradle",
+        markers: &["build.gradle", "build.gradle.kts", "settings.gradle", "settings.gradle.kts"],
+        files_patterns: &[],
+        dirs_patterns: &["build$", "\\.gradle$"],
+        marker_dirs: &["build$", "\\.gradle$"],
+    },
+    Ecosystem {
+        name: "java-maven",
+        markers: &["pom.xml"],
+        files_patterns: &[],
+        dirs_patterns: &["target$"],
+        marker_dirs: &["target$"],
+    },
+    Ecosystem {
+        name: "cmake",
+        markers: &["CMakeLists.txt"],
+        files_patterns: &["\\.o$", "\\.a$"],
+        dirs_patterns: &["build$", "cmake-build-[^/]*$", "CMakeFiles$"],
+        marker_dirs: &["build$", "cmake-build-[^/]*$"],
+    },
+    Ecosystem {
+        name: "go",
+        markers: &["go.mod"],
+        files_patterns: &["\\.test$"],
+        dirs_patterns: &[],
+        marker_dirs: &[],
+    },
+    Ecosystem {
+        name: "dotnet",
+        markers: &["*.csproj", "*.fsproj", "*.vbproj", "*.sln"],
+        files_patterns: &[],
+        dirs_patterns: &["bin$", "obj$"],
+        marker_dirs: &["bin$", "obj$"],
+    },
+];
+
+/// Look up a preset by name.
+pub fn find(name: &str) -> Option<&'static Ecosystem> {
+    ECOSYSTEMS.iter().find(|ecosystem| ecosystem.name == name)
+}
+
+pub fn names() -> Vec<&'static str> {
+    ECOSYSTEMS.iter().map(|ecosystem| ecosystem.name).collect()
+}
+
+/// Whether `file_name` matches `marker`; `*.ext` markers match by extension.
+pub fn matches_marker(file_name: &str, marker: &str) -> bool {
+    match marker.strip_prefix('*') {
+        Some(extension) => file_name.ends_with(extension),
+        None => file_name == marker,
+    }
+}
+
+impl Ecosystem {
+    pub fn is_marker(&self, file_name: &str) -> bool {
+        self.markers.iter().any(|marker| matches_marker(file_name, marker))
+    }
+
+    /// Patterns for one of the `Patterns` lists, by field name.
+    pub fn patterns(&self, list: &str) -> &'static [&'static str] {
+        match list {
+            "files_patterns" => self.files_patterns,
+            "dirs_patterns" => self.dirs_patterns,
+            _ => &[],
+        }
+    }
+}
@@ -0,0 +1,129 @@
+use colored::*;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use tokio::fs;
+use walkdir::WalkDir;
+
+use crate::config::{self, BackupConfig, Config, Patterns};
+use crate::ecosystems::{Ecosystem, ECOSYSTEMS};
+
+/// Directories never descended into while looking for markers: they are
+/// either artifacts themselves or too large to be worth walking.
+const SKIPPED_DIRS: &[&str] = &["node_modules", "target", "build", "dist", "__pycache__", "venv"];
+
+pub struct Initializer {
+    root: PathBuf,
+    output: Option<PathBuf>,
+    force: bool,
+}
+
+impl Initializer {
+    pub fn new(root: PathBuf) -> Self {
+        Self {
+            root,
+            output: None,
+            force: false,
+        }
+    }
+
+    /// Where to write the config; defaults to the user configuration file.
+    pub fn output(mut self, output: Option<PathBuf>) -> Self {
+        self.output = output;
+        self
+    }
+
+    /// Overwrite an existing file at the output path.
+    pub fn force(mut self, force: bool) -> Self {
+        self.force = force;
+        self
+    }
+
+    /// Count the projects of each ecosystem found under the root.
+    fn detect(&self) -> BTreeMap<usize, usize> {
+        let mut found = BTreeMap::new();
+
+        let walker = WalkDir::new(&self.root).into_iter().filter_entry(|entry| {
+            if entry.depth() == 0 || !entry.file_type().is_dir() {
+                return true;
+            }
+            let name = entry.file_name().to_string_lossy();
+            !name.starts_with('.') && !SKIPPED_DIRS.contains(&name.as_ref())
+        });
+
+        for entry in walker.filter_map(Result::ok) {
+            if !entry.file_type().is_file() {
+                continue;
+            }
+            let file_name = entry.file_name().to_string_lossy();
+            for (index, ecosystem) in ECOSYSTEMS.iter().enumerate() {
+                if ecosystem.is_marker(&file_name) {
+                    *found.entry(index).or_insert(0) += 1;
+                }
+            }
+        }
+
+        found
+    }
+
+    pub async fn init(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let root = self.root.canonicalize()
+            .map_err(|e| format!("Failed to access {}: {}", self.root.display(), e))?;
+
+        let output = match &self.output {
+            Some(output) => output.clone(),
+            None => config::user_config_dir()
+                .ok_or("Cannot determine the user configuration directory, pass --output")?
+                .join(format!("{}.json", config::CONFIG_FILE_STEM)),
+        };
+        if output.exists() && !self.force {
+            return Err(format!("{} already exists, pass --force to overwrite it", output.display()).into());
+        }
+
+        println!("\n{} {}", "🔍 Detecting ecosystems in".bright_blue().bold(), root.display().to_string().bright_white());
+        let found = self.detect();
+        if found.is_empty() {
+            return Err(format!("No known project markers found under {}", root.display()).into());
+        }
+
+        let ecosystems: Vec<&Ecosystem> = found.keys().map(|&index| &ECOSYSTEMS[index]).collect();
+        for (&index, count) in &found {
+            println!(
+                "  {} {}: {} project(s)",
+                "→".bright_black(),
+                ECOSYSTEMS[index].name.bright_magenta(),
+                count.to_string().bright_white()
+            );
+        }
+
+        let config = Config {
+            dir: root.display().to_string(),
+            presets: ecosystems.iter().map(|ecosystem| ecosystem.name.to_string()).collect(),
+            exclude: Patterns {
+                files_patterns: Vec::new(),
+                dirs_patterns: vec!["\\.git$".to_string()],
+                ..Patterns::default()
+            },
+            backup: BackupConfig::default(),
+            ..Config::default()
+        };
+
+        let contents = match output.extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => toml::to_string_pretty(&config)?,
+            _ => serde_json::to_string_pretty(&config)?,
+        };
+
+        if let Some(parent) = output.parent() {
+            if !parent.as_os_str().is_empty() {
+                fs::create_dir_all(parent).await
+                    .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+            }
+        }
+        fs::write(&output, contents).await
+            .map_err(|e| format!("Failed to write {}: {}", output.display(), e))?;
+
+        println!("\n{} {}", "📝 Configuration written to".bright_green(), output.display().to_string().bright_white());
+        println!("  {} Review it with: project-cleaner config show --config {}", "→".bright_black(), output.display());
+
+        Ok(())
+    }
+}
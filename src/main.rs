@@ -1,36 +1,34 @@
-use serde::Deserialize;
-use tokio::io::AsyncReadExt;
 use std::path::PathBuf;
 use colored::*;
-use clap::{Command, Arg};
+use clap::{Command, Arg, ArgMatches};
 use std::env;
 
 mod cleaner;
+mod config;
+mod ecosystems;
+mod init;
+mod prompt;
+mod prune;
 mod restore;
+mod verify;
 
-#[derive(Deserialize, Clone)]
-pub struct Config {
-    dir: String,
-    target: Patterns,
-    exclude: Patterns,
-    backup: BackupConfig,
-}
+use cleaner::types::ProjectOrder;
+use config::{BackupMode, Config, ConfigError, LowSpaceAction, Overrides, SelectionOrder, EXIT_CONFIG};
 
-#[derive(Deserialize, Clone)]
-pub struct Patterns {
-    files_patterns: Vec<String>,
-    dirs_patterns: Vec<String>,
-}
+#[tokio::main]
+async fn main() {
+    if let Err(e) = run().await {
+        if let Some(config_error) = e.downcast_ref::<ConfigError>() {
+            config_error.display_report();
+            std::process::exit(EXIT_CONFIG);
+        }
 
-#[derive(Deserialize, Clone)]
-pub struct BackupConfig {
-    enabled: bool,
-    dir: String,
-    versioning: bool,
+        eprintln!("{}", format!("❌ Error: {}", e).red());
+        std::process::exit(1);
+    }
 }
 
-#[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
+async fn run() -> Result<(), Box<dyn std::error::Error>> {
     let app = Command::new("project-cleaner")
         .version(env!("CARGO_PKG_VERSION"))
         .author("Da4ndo <contact@da4ndo.com>")
@@ -39,76 +37,521 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             .short('r')
             .long("restore")
             .action(clap::ArgAction::SetTrue)
-            .help("Restore files from backup"));
+            .help("Restore files from backup"))
+        .arg(Arg::new("dry-run")
+            .short('n')
+            .long("dry-run")
+            .action(clap::ArgAction::SetTrue)
+            .conflicts_with("restore")
+            .help("Show what would be deleted and backed up without touching the filesystem"))
+        .arg(Arg::new("plan-output")
+            .long("plan-output")
+            .value_name("FILE")
+            .value_parser(clap::value_parser!(PathBuf))
+            .requires("dry-run")
+            .help("Write the dry-run plan as JSON to FILE"))
+        .arg(Arg::new("git-ignored-only")
+            .long("git-ignored-only")
+            .action(clap::ArgAction::SetTrue)
+            .conflicts_with("restore")
+            .help("Only clean paths git ignores; never anything tracked, even if a pattern matches"))
+        .arg(Arg::new("project")
+            .long("project")
+            .value_name("REGEX")
+            .action(clap::ArgAction::Append)
+            .conflicts_with("restore")
+            .help("Only clean projects whose root path matches REGEX (repeatable)"))
+        .arg(Arg::new("sort")
+            .long("sort")
+            .value_name("ORDER")
+            .value_parser(["name", "size", "items"])
+            .default_value("name")
+            .help("Order of projects in the summary: name, size (largest first) or items"))
+        .arg(Arg::new("yes")
+            .short('y')
+            .long("yes")
+            .action(clap::ArgAction::SetTrue)
+            .global(true)
+            .help("Answer yes to all confirmation prompts"))
+        .arg(Arg::new("non-interactive")
+            .long("non-interactive")
+            .action(clap::ArgAction::SetTrue)
+            .global(true)
+            .help("Never prompt; fail if a question cannot be answered from the flags (implied when stdin is not a TTY)"))
+        .arg(Arg::new("backup-version")
+            .long("backup-version")
+            .value_name("latest|TIMESTAMP")
+            .requires("restore")
+            .help("Backup version to restore from, e.g. latest or 20240131_235959"))
+        .arg(Arg::new("config")
+            .short('c')
+            .long("config")
+            .value_name("FILE")
+            .value_parser(clap::value_parser!(PathBuf))
+            .global(true)
+            .help("Use this configuration file instead of the default one"))
+        .arg(Arg::new("dir")
+            .short('d')
+            .long("dir")
+            .value_name("DIR")
+            .action(clap::ArgAction::Append)
+            .global(true)
+            .help("Project directory to clean instead of the configured one (repeatable)"))
+        .arg(Arg::new("preset")
+            .short('p')
+            .long("preset")
+            .value_name("NAME")
+            .action(clap::ArgAction::Append)
+            .global(true)
+            .help("Additional ecosystem preset to clean, see `presets list` (repeatable)"))
+        .arg(Arg::new("min-age")
+            .long("min-age")
+            .value_name("AGE")
+            .global(true)
+            .help("Only clean targets unused for at least AGE, e.g. 30d"))
+        .arg(Arg::new("min-size")
+            .long("min-size")
+            .value_name("SIZE")
+            .global(true)
+            .help("Skip targets smaller than SIZE, e.g. 1MiB"))
+        .arg(Arg::new("max-total")
+            .long("max-total")
+            .value_name("SIZE")
+            .global(true)
+            .help("Stop selecting targets once SIZE would be cleaned, e.g. 20GiB"))
+        .arg(Arg::new("target-file")
+            .long("target-file")
+            .value_name("REGEX")
+            .action(clap::ArgAction::Append)
+            .global(true)
+            .help("Additional file pattern to clean (repeatable)"))
+        .arg(Arg::new("target-dir")
+            .long("target-dir")
+            .value_name("REGEX")
+            .action(clap::ArgAction::Append)
+            .global(true)
+            .help("Additional directory pattern to clean (repeatable)"))
+        .arg(Arg::new("exclude-file")
+            .long("exclude-file")
+            .value_name("REGEX")
+            .action(clap::ArgAction::Append)
+            .global(true)
+            .help("Additional file pattern to protect (repeatable)"))
+        .arg(Arg::new("exclude-dir")
+            .long("exclude-dir")
+            .value_name("REGEX")
+            .action(clap::ArgAction::Append)
+            .global(true)
+            .help("Additional directory pattern to protect (repeatable)"))
+        .arg(Arg::new("no-backup")
+            .long("no-backup")
+            .action(clap::ArgAction::SetTrue)
+            .conflicts_with("backup-dir")
+            .global(true)
+            .help("Delete items without backing them up"))
+        .arg(Arg::new("backup-dir")
+            .long("backup-dir")
+            .value_name("DIR")
+            .global(true)
+            .help("Store backups in DIR instead of the configured directory"))
+        .subcommand(Command::new("config")
+            .about("Inspect the effective configuration without scanning")
+            .subcommand_required(true)
+            .subcommand(Command::new("show")
+                .about("Print the resolved configuration as JSON")
+                .arg(Arg::new("origins")
+                    .long("origins")
+                    .action(clap::ArgAction::SetTrue)
+                    .help("Show where every value comes from")))
+            .subcommand(Command::new("check")
+                .about("Validate the resolved configuration")))
+        .subcommand(Command::new("presets")
+            .about("Inspect the built-in ecosystem presets")
+            .subcommand_required(true)
+            .subcommand(Command::new("list")
+                .about("Show every preset and the patterns it expands to")))
+        .subcommand(Command::new("init")
+            .about("Generate a starter configuration from the ecosystems found in a directory")
+            .arg(Arg::new("path")
+                .value_name("PATH")
+                .value_parser(clap::value_parser!(PathBuf))
+                .default_value(".")
+                .help("Directory to inspect; becomes the configured project directory"))
+            .arg(Arg::new("output")
+                .short('o')
+                .long("output")
+                .value_name("FILE")
+                .value_parser(clap::value_parser!(PathBuf))
+                .help("Where to write the configuration (.json or .toml); defaults to the user configuration file"))
+            .arg(Arg::new("force")
+                .short('f')
+                .long("force")
+                .action(clap::ArgAction::SetTrue)
+                .help("Overwrite the output file if it exists")))
+        .subcommand(Command::new("prune")
+            .about("Remove the backup versions backup.retention no longer keeps")
+            .arg(Arg::new("dry-run")
+                .short('n')
+                .long("dry-run")
+                .action(clap::ArgAction::SetTrue)
+                .help("Show which versions would be removed without deleting anything")))
+        .subcommand(Command::new("verify")
+            .about("Check stored backups against the checksums recorded when they were made")
+            .arg(Arg::new("backup-version")
+                .long("backup-version")
+                .value_name("latest|TIMESTAMP")
+                .help("Only check this version instead of all of them")));
 
     let matches = app.get_matches();
+    match matches.subcommand() {
+        Some(("config", config_matches)) => return config_command(config_matches).await,
+        Some(("presets", _)) => {
+            print_presets();
+            return Ok(());
+        }
+        Some(("init", init_matches)) => {
+            let initializer = init::Initializer::new(init_matches.get_one::<PathBuf>("path").cloned().unwrap_or_default())
+                .output(init_matches.get_one::<PathBuf>("output").cloned())
+                .force(init_matches.get_flag("force"));
+            return initializer.init().await;
+        }
+        Some(("prune", prune_matches)) => return prune_command(prune_matches).await,
+        Some(("verify", verify_matches)) => return verify_command(verify_matches).await,
+        _ => {}
+    }
+
+    let interaction = prompt::Interaction::new(
+        matches.get_flag("yes"),
+        matches.get_flag("non-interactive"),
+    );
+
+    // Discover configuration layers; command-line overrides go on top of them
+    let sources = config::ConfigSources::discover(matches.get_one::<PathBuf>("config").cloned(), true).await?;
+    let overrides = overrides_from(&matches);
 
-    // Load configuration
-    let config = load_config().await?;
+    let dirs = values_of(&matches, "dir");
+    if dirs.len() > 1 && (matches.get_flag("restore") || matches.contains_id("plan-output")) {
+        return Err("--restore and --plan-output accept a single --dir".into());
+    }
 
     if matches.get_flag("restore") {
+        let config = sources.load(dirs.first().map(String::as_str), &overrides).await?;
+        config.validate()?;
+
         println!("{}", "Starting restore process...".bright_cyan());
-        let restorer = restore::Restorer::new(config);
+        let restorer = restore::Restorer::new(config)
+            .interaction(interaction)
+            .version(matches.get_one::<String>("backup-version").cloned());
         restorer.restore().await?;
         println!("{}", "Restore process completed successfully.".bright_green());
     } else {
-        // Print configuration
-        println!("{}", "📝 Configuration:".bright_blue().bold());
-        println!("  {} {}: {}", "→".bright_black(), "Project directory".yellow(), config.dir.yellow());
-        println!("  {} {}", "→".bright_black(), "Backup settings:".bright_blue());
-        println!("    - {}: {}", "Enabled".bright_blue(), config.backup.enabled.to_string().bright_white());
-        println!("    - {}: {}", "Directory".bright_blue(), config.backup.dir.bright_white());
-        println!("    - {}: {}", "Versioning".bright_blue(), config.backup.versioning.to_string().bright_white());
-        
-        println!("\n  {} {}", "→".bright_black(), "File patterns to clean:".bright_blue());
-        for pattern in &config.target.files_patterns {
-            println!("    - {}", pattern.bright_white());
-        }
-        
-        println!("\n  {} {}", "→".bright_black(), "Directory patterns to clean:".bright_blue());
-        for pattern in &config.target.dirs_patterns {
-            println!("    - {}", pattern.bright_white());
-        }
-        
-        println!("\n  {} {}", "→".bright_black(), "Exception files:".bright_blue());
-        for pattern in &config.exclude.files_patterns {
-            println!("    - {}", pattern.bright_white());
-        }
-        
-        println!("\n  {} {}", "→".bright_black(), "Exception directories:".bright_blue());
-        for pattern in &config.exclude.dirs_patterns {
-            println!("    - {}", pattern.bright_white());
-        }
-
-        println!("\n{}", "Starting cleanup process...".bright_cyan());
-        // Create and run processor
-        let processor = cleaner::processor::Processor::new(config);
-        processor.process().await?;
-
-        println!("{}", "Cleanup process completed successfully.".bright_green());
+        let dirs: Vec<Option<&str>> = if dirs.is_empty() {
+            vec![None]
+        } else {
+            dirs.iter().map(|dir| Some(dir.as_str())).collect()
+        };
+
+        let dry_run = matches.get_flag("dry-run");
+        let project_order = match matches.get_one::<String>("sort").map(String::as_str) {
+            Some("size") => ProjectOrder::Size,
+            Some("items") => ProjectOrder::Items,
+            _ => ProjectOrder::Name,
+        };
+        for dir in dirs {
+            let config = sources.load(dir, &overrides).await?;
+            config.validate()?;
+            print_config(&config);
+
+            if dry_run {
+                println!("\n{} {}", "Starting dry run in".bright_cyan(), config.dir.bright_white());
+            } else {
+                println!("\n{} {}", "Starting cleanup process in".bright_cyan(), config.dir.bright_white());
+            }
+            // Create and run processor
+            let processor = cleaner::processor::Processor::new(config)
+                .dry_run(dry_run)
+                .git_ignored_only(matches.get_flag("git-ignored-only"))
+                .projects(values_of(&matches, "project"))
+                .project_order(project_order)
+                .interaction(interaction)
+                .plan_output(matches.get_one::<PathBuf>("plan-output").cloned());
+            processor.process().await?;
+        }
+
+        if dry_run {
+            println!("{}", "Dry run completed, nothing was changed.".bright_green());
+        } else {
+            println!("{}", "Cleanup process completed successfully.".bright_green());
+        }
     }
 
     Ok(())
 }
 
-//TODO Implement .conf rather .json
-async fn load_config() -> Result<Config, Box<dyn std::error::Error>> {
-    let config_path = if cfg!(debug_assertions) {
-        // Debug mode - look in current directory
-        PathBuf::from("clean.config.json")
+async fn config_command(matches: &ArgMatches) -> Result<(), Box<dyn std::error::Error>> {
+    let (command, matches) = matches.subcommand().expect("config requires a subcommand");
+    let show = command == "show";
+
+    // `show` keeps stdout clean so its JSON can be piped
+    let sources = config::ConfigSources::discover(matches.get_one::<PathBuf>("config").cloned(), !show).await?;
+    let overrides = overrides_from(matches);
+
+    let dirs = values_of(matches, "dir");
+    let dirs: Vec<Option<&str>> = if dirs.is_empty() {
+        vec![None]
     } else {
-        // Release mode - look in /etc/project-cleaner/
-        PathBuf::from("/etc/project-cleaner/clean.config.json")
+        dirs.iter().map(|dir| Some(dir.as_str())).collect()
     };
 
-    println!("\n{}", "📁 Loading configuration:".bright_blue().bold());
-    println!("  {} Path: {}", "→".bright_black(), config_path.display().to_string().bright_white());
-
-    let mut file = tokio::fs::File::open(&config_path).await
-        .map_err(|e| format!("Failed to open config file at {}: {}", config_path.display(), e))?;
-    
-    let mut contents = String::new();
-    file.read_to_string(&mut contents).await?;
-    
-    Ok(serde_json::from_str(&contents)?)
-}
\ No newline at end of file
+    let mut documents = Vec::new();
+    for dir in dirs {
+        let config = sources.load(dir, &overrides).await?;
+
+        if show {
+            documents.push(if matches.get_flag("origins") {
+                config.with_origins()?
+            } else {
+                serde_json::to_value(&config)?
+            });
+        } else {
+            config.validate()?;
+            println!(
+                "{} {}",
+                "✅ Configuration is valid for".bright_green(),
+                config.dir.bright_white()
+            );
+        }
+    }
+
+    if show {
+        let output = if documents.len() == 1 {
+            documents.remove(0)
+        } else {
+            serde_json::Value::Array(documents)
+        };
+        println!("{}", serde_json::to_string_pretty(&output)?);
+    }
+
+    Ok(())
+}
+
+async fn prune_command(matches: &ArgMatches) -> Result<(), Box<dyn std::error::Error>> {
+    let interaction = prompt::Interaction::new(
+        matches.get_flag("yes"),
+        matches.get_flag("non-interactive"),
+    );
+
+    let sources = config::ConfigSources::discover(matches.get_one::<PathBuf>("config").cloned(), true).await?;
+    let dirs = values_of(matches, "dir");
+    if dirs.len() > 1 {
+        return Err("prune accepts a single --dir".into());
+    }
+    let config = sources.load(dirs.first().map(String::as_str), &overrides_from(matches)).await?;
+    config.validate()?;
+
+    println!("{} {}", "Pruning backups in".bright_cyan(), config.backup.dir.bright_white());
+    let pruner = prune::Pruner::new(config)
+        .interaction(interaction)
+        .dry_run(matches.get_flag("dry-run"));
+    pruner.prune().await
+}
+
+async fn verify_command(matches: &ArgMatches) -> Result<(), Box<dyn std::error::Error>> {
+    let sources = config::ConfigSources::discover(matches.get_one::<PathBuf>("config").cloned(), true).await?;
+    let dirs = values_of(matches, "dir");
+    if dirs.len() > 1 {
+        return Err("verify accepts a single --dir".into());
+    }
+    let config = sources.load(dirs.first().map(String::as_str), &overrides_from(matches)).await?;
+    config.validate()?;
+
+    println!("{} {}", "Verifying backups in".bright_cyan(), config.backup.dir.bright_white());
+    let verifier = verify::Verifier::new(config)
+        .version(matches.get_one::<String>("backup-version").cloned());
+    verifier.verify().await
+}
+
+fn values_of(matches: &ArgMatches, id: &str) -> Vec<String> {
+    matches
+        .get_many::<String>(id)
+        .map(|values| values.cloned().collect())
+        .unwrap_or_default()
+}
+
+fn overrides_from(matches: &ArgMatches) -> Overrides {
+    Overrides {
+        presets: values_of(matches, "preset"),
+        min_age: matches.get_one::<String>("min-age").cloned(),
+        min_size: matches.get_one::<String>("min-size").cloned(),
+        max_total: matches.get_one::<String>("max-total").cloned(),
+        target_files: values_of(matches, "target-file"),
+        target_dirs: values_of(matches, "target-dir"),
+        exclude_files: values_of(matches, "exclude-file"),
+        exclude_dirs: values_of(matches, "exclude-dir"),
+        no_backup: matches.get_flag("no-backup"),
+        backup_dir: matches.get_one::<String>("backup-dir").cloned(),
+    }
+}
+
+fn print_config(config: &Config) {
+    let origin = |pointer: &str| format!("({})", config.origin(pointer)).bright_black();
+
+    println!("\n{}", "📝 Configuration:".bright_blue().bold());
+    println!("  {} {}: {} {}", "→".bright_black(), "Project directory".yellow(), config.dir.yellow(), origin("/dir"));
+    println!("  {} {}", "→".bright_black(), "Configuration files:".bright_blue());
+    for source in &config.sources {
+        println!("    - {}", source.display().to_string().bright_white());
+    }
+    println!("  {} {}", "→".bright_black(), "Backup settings:".bright_blue());
+    println!("    - {}: {} {}", "Enabled".bright_blue(), config.backup.enabled.to_string().bright_white(), origin("/backup/enabled"));
+    let mode = match config.backup.mode {
+        BackupMode::Directory => "directory",
+        BackupMode::Trash => "trash",
+    };
+    println!("    - {}: {} {}", "Mode".bright_blue(), mode.bright_white(), origin("/backup/mode"));
+    println!("    - {}: {} {}", "Directory".bright_blue(), config.backup.dir.bright_white(), origin("/backup/dir"));
+    println!("    - {}: {} {}", "Versioning".bright_blue(), config.backup.versioning.to_string().bright_white(), origin("/backup/versioning"));
+    println!("    - {}: {} {}", "Verify copies".bright_blue(), config.backup.verify.to_string().bright_white(), origin("/backup/verify"));
+    if let Some(max_size) = &config.backup.max_size {
+        println!("    - {}: {} {}", "Max size".bright_blue(), max_size.bright_white(), origin("/backup/max_size"));
+    }
+    let on_low_space = match config.backup.on_low_space {
+        LowSpaceAction::Abort => "abort",
+        LowSpaceAction::Prune => "prune oldest versions",
+        LowSpaceAction::Delete => "delete without backup",
+    };
+    println!("    - {}: {} {}", "On low space".bright_blue(), on_low_space.bright_white(), origin("/backup/on_low_space"));
+
+    if !config.presets.is_empty() {
+        println!("\n  {} {}", "→".bright_black(), "Presets:".bright_blue());
+        for (index, preset) in config.presets.iter().enumerate() {
+            println!("    - {} {}", preset.bright_white(), origin(&format!("/presets/{}", index)));
+        }
+    }
+
+    for (title, pointer, patterns) in [
+        ("File patterns to clean:", "/target/files_patterns", &config.target.files_patterns),
+        ("Directory patterns to clean:", "/target/dirs_patterns", &config.target.dirs_patterns),
+        ("Exception files:", "/exclude/files_patterns", &config.exclude.files_patterns),
+        ("Exception directories:", "/exclude/dirs_patterns", &config.exclude.dirs_patterns),
+    ] {
+        println!("\n  {} {}", "→".bright_black(), title.bright_blue());
+        for (index, pattern) in patterns.iter().enumerate() {
+            println!("    - {} {}", pattern.bright_white(), origin(&format!("{}/{}", pointer, index)));
+        }
+    }
+
+    if config.age.min_age.is_some() || !config.age.rules.is_empty() {
+        println!("\n  {} {}", "→".bright_black(), "Minimum age:".bright_blue());
+        if let Some(min_age) = &config.age.min_age {
+            println!("    - {}: {} {}", "All targets".bright_blue(), min_age.bright_white(), origin("/age/min_age"));
+        }
+        for (index, rule) in config.age.rules.iter().enumerate() {
+            println!(
+                "    - {}: {} {}",
+                rule.pattern.bright_white(),
+                rule.min_age.bright_white(),
+                origin(&format!("/age/rules/{}/min_age", index))
+            );
+        }
+    }
+
+    if config.size.min_size.is_some() || !config.size.rules.is_empty() || config.size.max_total.is_some() {
+        println!("\n  {} {}", "→".bright_black(), "Size limits:".bright_blue());
+        if let Some(min_size) = &config.size.min_size {
+            println!("    - {}: {} {}", "Minimum size".bright_blue(), min_size.bright_white(), origin("/size/min_size"));
+        }
+        for (index, rule) in config.size.rules.iter().enumerate() {
+            println!(
+                "    - {}: {} {}",
+                rule.pattern.bright_white(),
+                rule.min_size.bright_white(),
+                origin(&format!("/size/rules/{}/min_size", index))
+            );
+        }
+        if let Some(max_total) = &config.size.max_total {
+            let order = match config.size.order {
+                SelectionOrder::Largest => "largest first",
+                SelectionOrder::Oldest => "oldest first",
+            };
+            println!(
+                "    - {}: {}, {} {}",
+                "Run budget".bright_blue(),
+                max_total.bright_white(),
+                order.bright_white(),
+                origin("/size/max_total")
+            );
+        }
+    }
+
+    if config.git.skip_in_progress || config.git.skip_newer_than_commit {
+        println!("\n  {} {}", "→".bright_black(), "Git repositories:".bright_blue());
+        println!(
+            "    - {}: {} {}",
+            "Skip while a rebase/merge/bisect is in progress".bright_blue(),
+            config.git.skip_in_progress.to_string().bright_white(),
+            origin("/git/skip_in_progress")
+        );
+        println!(
+            "    - {}: {} {}",
+            "Skip matches newer than the last commit".bright_blue(),
+            config.git.skip_newer_than_commit.to_string().bright_white(),
+            origin("/git/skip_newer_than_commit")
+        );
+    }
+
+    let retention = &config.backup.retention;
+    if !retention.is_empty() {
+        println!("\n  {} {}", "→".bright_black(), "Backup retention:".bright_blue());
+        for (name, count) in [
+            ("keep_last", retention.keep_last),
+            ("keep_daily", retention.keep_daily),
+            ("keep_weekly", retention.keep_weekly),
+            ("keep_monthly", retention.keep_monthly),
+        ] {
+            if let Some(count) = count {
+                println!("    - {}: {} {}", name.bright_blue(), count.to_string().bright_white(), origin(&format!("/backup/retention/{}", name)));
+            }
+        }
+        for (name, limit) in [("max_age", &retention.max_age), ("max_total_size", &retention.max_total_size)] {
+            if let Some(limit) = limit {
+                println!("    - {}: {} {}", name.bright_blue(), limit.bright_white(), origin(&format!("/backup/retention/{}", name)));
+            }
+        }
+    }
+
+    if !config.marker_rules.is_empty() {
+        println!("\n  {} {}", "→".bright_black(), "Only cleaned next to a marker:".bright_blue());
+        for (index, rule) in config.marker_rules.iter().enumerate() {
+            println!(
+                "    - {} ← {} {}",
+                rule.pattern.bright_white(),
+                rule.markers.join(", ").bright_white(),
+                origin(&format!("/marker_rules/{}/pattern", index))
+            );
+        }
+    }
+}
+
+fn print_presets() {
+    println!("{}", "📦 Available presets:".bright_blue().bold());
+    for ecosystem in ecosystems::ECOSYSTEMS {
+        println!("\n  {} {}", "→".bright_black(), ecosystem.name.bright_magenta().bold());
+        println!("    - {}: {}", "Detected by".bright_blue(), ecosystem.markers.join(", ").bright_white());
+        for (title, patterns) in [
+            ("File patterns", ecosystem.files_patterns),
+            ("Directory patterns", ecosystem.dirs_patterns),
+        ] {
+            if !patterns.is_empty() {
+                println!("    - {}: {}", title.bright_blue(), patterns.join(", ").bright_white());
+            }
+        }
+        if !ecosystem.marker_dirs.is_empty() {
+            println!(
+                "    - {}: {}",
+                "Only next to a marker".bright_blue(),
+                ecosystem.marker_dirs.join(", ").bright_white()
+            );
+        }
+    }
+}
@@ -0,0 +1,96 @@
+use colored::*;
+use inquire::{Confirm, InquireError, MultiSelect, Select};
+use std::io::IsTerminal;
+
+/// Decides how questions are answered: by the user on a TTY, automatically
+/// with `--yes`, or not at all (an error instead of a hanging prompt).
+#[derive(Debug, Clone, Copy)]
+pub struct Interaction {
+    assume_yes: bool,
+    interactive: bool,
+}
+
+impl Default for Interaction {
+    fn default() -> Self {
+        Self::new(false, false)
+    }
+}
+
+impl Interaction {
+    pub fn new(assume_yes: bool, non_interactive: bool) -> Self {
+        Self {
+            assume_yes,
+            interactive: !non_interactive && std::io::stdin().is_terminal(),
+        }
+    }
+
+    pub fn confirm(&self, message: &str, help: Option<&str>) -> Result<bool, Box<dyn std::error::Error>> {
+        if self.assume_yes {
+            println!("{} {}", message, "yes (--yes)".bright_black());
+            return Ok(true);
+        }
+
+        if !self.interactive {
+            return Err(format!(
+                "Confirmation required (\"{}\") but no terminal is available; pass --yes to proceed non-interactively",
+                message
+            )
+            .into());
+        }
+
+        let mut prompt = Confirm::new(message).with_default(false);
+        if let Some(help) = help {
+            prompt = prompt.with_help_message(help);
+        }
+        Ok(prompt.prompt()?)
+    }
+
+    /// Ask the user to pick one of `options`, returning its index.
+    /// `hint` names the flag that answers this question without a terminal.
+    pub fn select(&self, message: &str, options: Vec<String>, hint: &str) -> Result<usize, Box<dyn std::error::Error>> {
+        if !self.interactive {
+            return Err(format!(
+                "Selection required (\"{}\") but no terminal is available; pass {}",
+                message, hint
+            )
+            .into());
+        }
+
+        let options_clone = options.clone();
+        let selected = Select::new(message, options)
+            .with_help_message("↑↓ to move, enter to select")
+            .prompt()?;
+
+        Ok(options_clone.iter().position(|x| x == &selected).unwrap())
+    }
+
+    /// Let the user untick some of `options`, all ticked to begin with,
+    /// returning the indices kept. With `--yes` everything is kept; cancelling
+    /// the prompt keeps nothing.
+    pub fn multi_select(&self, message: &str, options: Vec<String>) -> Result<Vec<usize>, Box<dyn std::error::Error>> {
+        if self.assume_yes {
+            println!("{} {}", message, format!("all {} (--yes)", options.len()).bright_black());
+            return Ok((0..options.len()).collect());
+        }
+
+        if !self.interactive {
+            return Err(format!(
+                "Selection required (\"{}\") but no terminal is available; pass --yes to proceed non-interactively",
+                message
+            )
+            .into());
+        }
+
+        let selected = MultiSelect::new(message, options)
+            .with_all_selected_by_default()
+            .with_page_size(15)
+            .with_help_message("↑↓ to move, space to toggle, → all, ← none, type to filter, enter to confirm")
+            .raw_prompt();
+
+        match selected {
+            Ok(selected) => Ok(selected.into_iter().map(|option| option.index).collect()),
+            Err(InquireError::OperationCanceled) => Ok(Vec::new()),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
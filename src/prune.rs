@@ -0,0 +1,154 @@
+use colored::*;
+use humansize::{format_size, BINARY};
+use std::path::{Path, PathBuf};
+
+use crate::cleaner::retention::RetentionPolicy;
+use crate::cleaner::versions::{self, BackupVersion};
+use crate::config::{BackupMode, Config};
+use crate::prompt::Interaction;
+
+/// Removes the backup versions `backup.retention` no longer keeps.
+pub struct Pruner {
+    config: Config,
+    interaction: Interaction,
+    dry_run: bool,
+}
+
+impl Pruner {
+    pub fn new(config: Config) -> Self {
+        Self {
+            config,
+            interaction: Interaction::default(),
+            dry_run: false,
+        }
+    }
+
+    pub fn interaction(mut self, interaction: Interaction) -> Self {
+        self.interaction = interaction;
+        self
+    }
+
+    /// Only report what would be removed.
+    pub fn dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    pub async fn prune(&self) -> Result<(), Box<dyn std::error::Error>> {
+        if !self.config.backup.enabled || self.config.backup.mode == BackupMode::Trash || !self.config.backup.versioning {
+            println!("{}", "⚠️  Pruning only applies to versioned backups in a backup directory.".yellow());
+            return Ok(());
+        }
+        if self.config.backup.retention.is_empty() {
+            println!("{}", "⚠️  No retention policy configured (backup.retention), nothing to prune.".yellow());
+            return Ok(());
+        }
+
+        let backup_dir = PathBuf::from(shellexpand::tilde(&self.config.backup.dir).into_owned());
+        let versions = versions::list(&backup_dir).await?;
+        if versions.is_empty() {
+            println!("{}", "⚠️  No backup versions found.".yellow());
+            return Ok(());
+        }
+
+        let usages = versions
+            .iter()
+            .map(BackupVersion::usage)
+            .collect::<std::io::Result<Vec<_>>>()
+            .map_err(|e| format!("Failed to measure backups in {}: {}", backup_dir.display(), e))?;
+        let policy = RetentionPolicy::new(&self.config.backup.retention);
+        let decisions = policy.apply(&versions, &usages, chrono::Local::now().naive_local());
+
+        println!("\n{}:", "📋 Backup versions".bright_blue().bold());
+        for ((version, usage), decision) in versions.iter().zip(&usages).zip(&decisions) {
+            let (icon, action) = if decision.keep {
+                ("✅", "Keep".green())
+            } else {
+                ("🗑️", "Remove".red())
+            };
+            println!(
+                "  {} {} {} {} {}",
+                icon,
+                action,
+                version.name.bright_white(),
+                format!("({})", format_size(usage.own, BINARY)).bright_black(),
+                format!("- {}", decision.reason).bright_black()
+            );
+        }
+
+        let removed: Vec<&BackupVersion> = versions
+            .iter()
+            .zip(&decisions)
+            .filter(|(_, decision)| !decision.keep)
+            .map(|(version, _)| version)
+            .collect();
+        let size_before = RetentionPolicy::total_size(&usages, |_| true);
+        let size_after = RetentionPolicy::total_size(&usages, |index| decisions[index].keep);
+
+        println!("\n{}:", "📊 Prune summary".bright_blue().bold());
+        println!("  {} Versions kept: {}", "→".bright_black(), (versions.len() - removed.len()).to_string().bright_white());
+        println!("  {} Versions to remove: {}", "→".bright_black(), removed.len().to_string().bright_white());
+        println!(
+            "  {} Space to reclaim: {}",
+            "→".bright_black(),
+            format_size(size_before - size_after, BINARY).bright_white()
+        );
+
+        if removed.is_empty() {
+            println!("{}", "✅ Nothing to prune.".green());
+            return Ok(());
+        }
+        if self.dry_run {
+            println!("{}", "ℹ️  Dry run: no backups were removed.".bright_cyan());
+            return Ok(());
+        }
+
+        let confirm = self.interaction.confirm(
+            &format!("🤔 Remove {} backup version(s)?", removed.len()),
+            Some("Removed versions cannot be restored"),
+        )?;
+        if !confirm {
+            println!("{}", "🚫 Prune cancelled.".yellow());
+            return Ok(());
+        }
+
+        let mut success_count = 0;
+        let mut error_count = 0;
+        for version in removed {
+            match version.remove().await {
+                Ok(()) => {
+                    println!("  {} {}", "✅".bold(), format!("Removed {}", version.name).green());
+                    success_count += 1;
+                }
+                Err(e) => {
+                    println!("  {} {}", "❌".bold(), format!("Failed to remove {}: {}", version.path.display(), e).red());
+                    error_count += 1;
+                }
+            }
+        }
+
+        self.collect_garbage(&backup_dir).await?;
+
+        println!("\n{}", "━".repeat(80).bright_black());
+        println!("📊 Prune Summary:");
+        println!("  {} Removed: {}", "→".bright_blue(), success_count.to_string().green());
+        println!("  {} Failed: {}", "→".bright_blue(), error_count.to_string().red());
+        println!("{}", "━".repeat(80).bright_black());
+        Ok(())
+    }
+
+    /// Delete dedup blobs that no remaining version refers to.
+    async fn collect_garbage(&self, backup_dir: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        let (count, size) = versions::collect_garbage(backup_dir)
+            .await
+            .map_err(|e| format!("Failed to remove unused blobs: {}", e))?;
+        if count > 0 {
+            println!(
+                "  {} {}",
+                "🧹".bold(),
+                format!("Removed {} unused blob(s), {}", count, format_size(size, BINARY)).green()
+            );
+        }
+        Ok(())
+    }
+}
@@ -1,23 +1,70 @@
 use colored::*;
-use inquire::{Select, Confirm};
 use std::path::{Path, PathBuf};
 use tokio::fs;
 use walkdir::WalkDir;
-use std::collections::HashMap;
-use chrono::NaiveDateTime;
 use regex::Regex;
 use crate::create_regex;
 use humansize::{format_size, BINARY};
 
-use crate::Config;
+use crate::prompt::Interaction;
+use crate::cleaner::{archive, attributes, manifest, store, versions};
+use crate::cleaner::types::BackupEntry;
+use crate::cleaner::trash;
+use crate::config::{BackupMode, Config};
+
+/// An item of a backup version and where it goes back to.
+struct RestoreItem {
+    /// Path inside the backup version.
+    backup_path: PathBuf,
+    target_path: PathBuf,
+    size: u64,
+}
 
 pub struct Restorer {
     config: Config,
+    interaction: Interaction,
+    version: Option<String>,
 }
 
 impl Restorer {
     pub fn new(config: Config) -> Self {
-        Self { config }
+        Self {
+            config,
+            interaction: Interaction::default(),
+            version: None,
+        }
+    }
+
+    pub fn interaction(mut self, interaction: Interaction) -> Self {
+        self.interaction = interaction;
+        self
+    }
+
+    /// Restore from this version (`latest` or a `%Y%m%d_%H%M%S` timestamp) instead of asking.
+    pub fn version(mut self, version: Option<String>) -> Self {
+        self.version = version;
+        self
+    }
+
+    fn find_version(versions: &[(String, PathBuf)], requested: &str) -> Result<PathBuf, Box<dyn std::error::Error>> {
+        if requested == "latest" {
+            // Versions are sorted newest first
+            return Ok(versions[0].1.clone());
+        }
+
+        versions
+            .iter()
+            .find(|(timestamp, _)| timestamp == requested)
+            .map(|(_, path)| path.clone())
+            .ok_or_else(|| {
+                let available: Vec<&str> = versions.iter().map(|(timestamp, _)| timestamp.as_str()).collect();
+                format!(
+                    "Backup version '{}' not found. Available versions: latest, {}",
+                    requested,
+                    available.join(", ")
+                )
+                .into()
+            })
     }
 
     async fn get_backup_versions(&self, backup_dir: &Path) -> Result<Vec<(String, PathBuf)>, std::io::Error> {
@@ -33,28 +80,48 @@ impl Restorer {
             return Ok(versions);
         }
 
-        let mut dir_reader = fs::read_dir(backup_dir).await?;
-        while let Some(entry) = dir_reader.next_entry().await? {
-            let path = entry.path();
-            
-            if path.is_dir() {
-                let dir_name = path.file_name()
-                    .and_then(|n| n.to_str())
-                    .unwrap_or("")
-                    .to_string();
-                
-                if NaiveDateTime::parse_from_str(&dir_name, "%Y%m%d_%H%M%S").is_ok() {
-                    versions.push((dir_name, path));
-                }
-            }
+        versions.extend(
+            versions::list(backup_dir)
+                .await?
+                .into_iter()
+                .map(|version| (version.name, version.path)),
+        );
+        Ok(versions)
+    }
+
+    /// Whether the version is a single archive or manifest file rather than a directory tree.
+    fn is_version_file(version_path: &Path) -> bool {
+        version_path.is_file()
+    }
+
+    /// Everything stored in a backup version, relative to the project directory.
+    fn get_backup_entries(version_path: &Path) -> Result<Vec<BackupEntry>, std::io::Error> {
+        if store::is_manifest(version_path) {
+            return store::list(version_path);
+        }
+        if Self::is_version_file(version_path) {
+            return archive::list(version_path);
         }
 
-        versions.sort_by(|a, b| b.0.cmp(&a.0));
-        Ok(versions)
+        let mut entries = Vec::new();
+        for entry in WalkDir::new(version_path).min_depth(1) {
+            let entry = entry?;
+            let metadata = entry.metadata()?;
+            if let Ok(relative) = entry.path().strip_prefix(version_path) {
+                entries.push(BackupEntry {
+                    path: relative.to_path_buf(),
+                    is_directory: metadata.is_dir(),
+                    size: if metadata.is_dir() { 0 } else { metadata.len() },
+                });
+            }
+        }
+        Ok(entries)
     }
 
-    fn get_backup_items(&self, version_dir: &Path) -> Result<HashMap<String, PathBuf>, std::io::Error> {
-        let mut items = HashMap::new();
+    /// Items of a version without a manifest, found by matching the current
+    /// target patterns against what it contains.
+    fn get_backup_items(&self, entries: &[BackupEntry]) -> Vec<RestoreItem> {
+        let mut items = Vec::new();
         let base_dir = PathBuf::from(&self.config.dir);
         
         // Create regex patterns from config
@@ -62,42 +129,57 @@ impl Restorer {
         let dir_patterns = create_regex!(&self.config.target.dirs_patterns);
         let mut processed_dirs = std::collections::HashSet::new();
 
-        for entry in WalkDir::new(version_dir) {
-            let entry = entry?;
-            let path = entry.path();
-            
-            if let Ok(relative) = path.strip_prefix(version_dir) {
-                let relative_str = relative.to_string_lossy();
-                let is_dir = path.is_dir();
-
-                // Check if any parent directory has already been processed
-                let should_skip = relative
-                    .ancestors()
-                    .any(|ancestor| processed_dirs.contains(&ancestor.to_string_lossy().to_string()));
-
-                if should_skip {
-                    continue;
-                }
+        for entry in entries {
+            let relative = entry.path.as_path();
+            let relative_str = relative.to_string_lossy();
+            let is_dir = entry.is_directory;
 
-                // Check if the path matches our patterns
-                let matches_pattern = if is_dir {
-                    dir_patterns.iter().any(|re| re.is_match(&relative_str))
-                } else {
-                    file_patterns.iter().any(|re| re.is_match(&relative_str))
-                };
-
-                if matches_pattern {
-                    let target_path = base_dir.join(relative);
-                    items.insert(relative.display().to_string(), target_path);
-                    
-                    if is_dir {
-                        processed_dirs.insert(relative.to_string_lossy().to_string());
-                    }
+            // Check if any parent directory has already been processed
+            let should_skip = relative
+                .ancestors()
+                .any(|ancestor| processed_dirs.contains(&ancestor.to_string_lossy().to_string()));
+
+            if should_skip {
+                continue;
+            }
+
+            // Check if the path matches our patterns
+            let matches_pattern = if is_dir {
+                dir_patterns.iter().any(|re| re.is_match(&relative_str))
+            } else {
+                file_patterns.iter().any(|re| re.is_match(&relative_str))
+            };
+
+            if matches_pattern {
+                items.push(RestoreItem {
+                    backup_path: relative.to_path_buf(),
+                    target_path: base_dir.join(relative),
+                    size: entries
+                        .iter()
+                        .filter(|entry| entry.path.starts_with(relative))
+                        .map(|entry| entry.size)
+                        .sum(),
+                });
+
+                if is_dir {
+                    processed_dirs.insert(relative.to_string_lossy().to_string());
                 }
             }
         }
 
-        Ok(items)
+        items
+    }
+
+    /// Where an entry stored at `path` inside a version is restored to: below
+    /// the target of the item containing it, if any.
+    fn destination_of(items: &[(PathBuf, PathBuf)], path: &Path) -> Option<PathBuf> {
+        let (backup_path, target_path) = items.iter().find(|(backup_path, _)| path.starts_with(backup_path))?;
+        let rest = path.strip_prefix(backup_path).ok()?;
+        if rest.as_os_str().is_empty() {
+            Some(target_path.clone())
+        } else {
+            Some(target_path.join(rest))
+        }
     }
 
     pub async fn restore(&self) -> Result<(), Box<dyn std::error::Error>> {
@@ -110,6 +192,19 @@ impl Restorer {
             return Ok(());
         }
 
+        if self.config.backup.mode == BackupMode::Trash {
+            let trash_dir = trash::home_trash().unwrap_or_else(|| PathBuf::from("~/.local/share/Trash"));
+            println!(
+                "{}",
+                format!(
+                    "⚠️  Items were moved to the trash ({}); restore them from your file manager.",
+                    trash_dir.display()
+                )
+                .yellow()
+            );
+            return Ok(());
+        }
+
         let backup_dir = PathBuf::from(shellexpand::tilde(&self.config.backup.dir).into_owned());
         if !backup_dir.exists() {
             println!("{}", format!("⚠️  Backup directory not found: {}", backup_dir.display()).red());
@@ -123,34 +218,62 @@ impl Restorer {
             return Ok(());
         }
 
-        let version_path = if self.config.backup.versioning {
+        let version_path = if !self.config.backup.versioning {
+            if self.version.is_some() {
+                println!("{}", "⚠️  Versioning is disabled, ignoring --backup-version.".yellow());
+            }
+            // Use the main backup directory directly
+            backup_dir
+        } else if let Some(requested) = &self.version {
+            let path = Self::find_version(&versions, requested)?;
+            println!("  {} Using backup version: {}", "→".bright_blue(), path.display().to_string().bright_white());
+            path
+        } else {
             // Let user select which version to restore from
             let version_options: Vec<String> = versions.iter()
                 .map(|(timestamp, _)| {
                     if timestamp == "current" {
                         "📁 Current backup".to_string()
-                    } else if let Ok(dt) = NaiveDateTime::parse_from_str(timestamp, "%Y%m%d_%H%M%S") {
-                        format!("📅 Backup from {}", dt.format("%Y-%m-%d %H:%M:%S"))
+                    } else if let Some((dt, sequence)) = versions::parse_name(timestamp) {
+                        match sequence {
+                            1 => format!("📅 Backup from {}", dt.format("%Y-%m-%d %H:%M:%S")),
+                            _ => format!("📅 Backup from {} (#{})", dt.format("%Y-%m-%d %H:%M:%S"), sequence),
+                        }
                     } else {
                         timestamp.to_string()
                     }
                 })
                 .collect();
 
-            let version_options_clone = version_options.clone();
-            let selected_version = Select::new("Select version to restore from:", version_options)
-                .with_help_message("↑↓ to move, enter to select")
-                .prompt()?;
-
-            let version_index = version_options_clone.iter().position(|x| x == &selected_version).unwrap();
+            let version_index = self.interaction.select(
+                "Select version to restore from:",
+                version_options,
+                "--backup-version latest|<timestamp>",
+            )?;
             versions[version_index].1.clone()
-        } else {
-            // Use the main backup directory directly
-            backup_dir
         };
 
-        // Get items in the selected version
-        let items = self.get_backup_items(&version_path)?;
+        // Get items in the selected version, from its manifest when it has one
+        let manifest_path = manifest::for_version(&version_path);
+        let items = if manifest_path.exists() {
+            manifest::read(&manifest_path)
+                .map_err(|e| format!("Failed to read manifest {}: {}", manifest_path.display(), e))?
+                .items
+                .into_iter()
+                .map(|item| RestoreItem {
+                    backup_path: item.backup_path,
+                    target_path: item.original_path,
+                    size: item.size,
+                })
+                .collect()
+        } else {
+            println!(
+                "{}",
+                "⚠️  This version has no manifest, matching the current target patterns against it.".yellow()
+            );
+            let entries = Self::get_backup_entries(&version_path)?;
+            self.get_backup_items(&entries)
+        };
         if items.is_empty() {
             println!("{}", "⚠️  No items found in this backup version.".yellow());
             return Ok(());
@@ -161,20 +284,12 @@ impl Restorer {
         let current_project_size = fs_extra::dir::get_size(&self.config.dir).unwrap_or(0);
 
         println!("\n📋 Items to restore:");
-        for (relative_path, target_path) in &items {
-            let source_path = version_path.join(relative_path);
-            let item_size = if source_path.is_dir() {
-                fs_extra::dir::get_size(&source_path).unwrap_or(0)
-            } else {
-                source_path.metadata()
-                    .map(|m| m.len())
-                    .unwrap_or(0)
-            };
-            total_restore_size += item_size;
+        for item in &items {
+            total_restore_size += item.size;
 
             println!("  {} {} → {}", "→".bright_blue(), 
-                relative_path.bright_white(),
-                target_path.display().to_string().bright_cyan()
+                item.backup_path.display().to_string().bright_white(),
+                item.target_path.display().to_string().bright_cyan()
             );
         }
 
@@ -189,10 +304,10 @@ impl Restorer {
             format_size(final_size, BINARY).bright_white());
 
         println!("\n{}", "━".repeat(80).bright_black());
-        let confirm = Confirm::new("🤔 Do you want to proceed with the restore?")
-            .with_default(false)
-            .with_help_message("This will overwrite existing files")
-            .prompt()?;
+        let confirm = self.interaction.confirm(
+            "🤔 Do you want to proceed with the restore?",
+            Some("This will overwrite existing files"),
+        )?;
 
         if !confirm {
             println!("{}", "🚫 Restore cancelled.".yellow());
@@ -203,54 +318,70 @@ impl Restorer {
         let mut success_count = 0;
         let mut error_count = 0;
 
-        for (relative_path, target_path) in items {
-            println!("\n{}", "Processing:".bright_blue());
-            println!("  {} Source: {}", "→".bright_blue(), relative_path.bright_white());
-            println!("  {} Target: {}", "→".bright_blue(), target_path.display().to_string().bright_white());
-
-            // Create parent directories if they don't exist
-            if let Some(parent) = target_path.parent() {
-                if !parent.exists() {
-                    match fs::create_dir_all(parent).await {
-                        Ok(_) => println!("  {} Created directory: {}", "📁".bold(), parent.display()),
-                        Err(e) => {
-                            println!("  {} {}", "❌".bold(), 
-                                format!("Failed to create directory {}: {}", parent.display(), e).red()
-                            );
-                            error_count += 1;
-                            continue;
-                        }
-                    }
+        if Self::is_version_file(&version_path) {
+            println!("  {} Extracting from {}", "📦".bold(), version_path.display().to_string().bright_white());
+            let selected: Vec<(PathBuf, PathBuf)> = items
+                .iter()
+                .map(|item| (item.backup_path.clone(), item.target_path.clone()))
+                .collect();
+            let version_file = version_path.clone();
+            let extracted = tokio::task::spawn_blocking(move || {
+                let destination_of = |path: &Path| Self::destination_of(&selected, path);
+                if store::is_manifest(&version_file) {
+                    // Manifests sit next to the `objects/` directory of their store
+                    let store_dir = version_file.parent().unwrap_or(Path::new("."));
+                    store::extract(store_dir, &version_file, destination_of)
+                } else {
+                    archive::extract(&version_file, destination_of)
                 }
-            }
-
-            let source_path = version_path.join(&relative_path);
-            if source_path.is_dir() {
-                let options = fs_extra::dir::CopyOptions::new()
-                    .overwrite(true)
-                    .content_only(false);
+            })
+            .await?;
 
-                match fs_extra::dir::copy(&source_path, target_path.parent().unwrap(), &options) {
-                    Ok(_) => {
-                        println!("  {} {}", "✅".bold(), "Directory restored successfully".green());
-                        success_count += 1;
-                    },
-                    Err(e) => {
-                        println!("  {} {}", "❌".bold(), 
-                            format!("Failed to restore directory: {}", e).red()
-                        );
-                        error_count += 1;
+            match extracted {
+                Ok(count) => {
+                    println!("  {} {}", "✅".bold(), format!("{} entries restored", count).green());
+                    success_count = items.len();
+                }
+                Err(e) => {
+                    println!("  {} {}", "❌".bold(), format!("Failed to extract {}: {}", version_path.display(), e).red());
+                    error_count = items.len();
+                }
+            }
+        } else {
+            for RestoreItem { backup_path, target_path, .. } in items {
+                let relative_path = backup_path.display().to_string();
+                println!("\n{}", "Processing:".bright_blue());
+                println!("  {} Source: {}", "→".bright_blue(), relative_path.bright_white());
+                println!("  {} Target: {}", "→".bright_blue(), target_path.display().to_string().bright_white());
+
+                // Create parent directories if they don't exist
+                if let Some(parent) = target_path.parent() {
+                    if !parent.exists() {
+                        match fs::create_dir_all(parent).await {
+                            Ok(_) => println!("  {} Created directory: {}", "📁".bold(), parent.display()),
+                            Err(e) => {
+                                println!("  {} {}", "❌".bold(), 
+                                    format!("Failed to create directory {}: {}", parent.display(), e).red()
+                                );
+                                error_count += 1;
+                                continue;
+                            }
+                        }
                     }
                 }
-            } else {
-                match fs::copy(&source_path, &target_path).await {
-                    Ok(_) => {
-                        println!("  {} {}", "✅".bold(), "File restored successfully".green());
+
+                let source_path = version_path.join(&relative_path);
+                let kind = if source_path.is_dir() { "Directory" } else { "File" };
+                // Keeps permissions, times, symlinks, hard links and extended attributes
+                let (from, to) = (source_path.clone(), target_path.clone());
+                match tokio::task::spawn_blocking(move || attributes::copy(&from, &to)).await? {
+                    Ok(()) => {
+                        println!("  {} {}", "✅".bold(), format!("{} restored successfully", kind).green());
                         success_count += 1;
                     },
                     Err(e) => {
                         println!("  {} {}", "❌".bold(), 
-                            format!("Failed to restore file: {}", e).red()
+                            format!("Failed to restore {}: {}", kind.to_lowercase(), e).red()
                         );
                         error_count += 1;
                     }
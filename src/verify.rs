@@ -0,0 +1,139 @@
+use colored::*;
+use std::path::PathBuf;
+
+use crate::cleaner::{manifest, versions};
+use crate::config::{BackupMode, Config};
+
+/// Checks stored backup versions against the checksums their manifests recorded.
+pub struct Verifier {
+    config: Config,
+    version: Option<String>,
+}
+
+impl Verifier {
+    pub fn new(config: Config) -> Self {
+        Self {
+            config,
+            version: None,
+        }
+    }
+
+    /// Only check this version (`latest` or a `%Y%m%d_%H%M%S` timestamp).
+    pub fn version(mut self, version: Option<String>) -> Self {
+        self.version = version;
+        self
+    }
+
+    pub async fn verify(&self) -> Result<(), Box<dyn std::error::Error>> {
+        if !self.config.backup.enabled || self.config.backup.mode == BackupMode::Trash {
+            println!("{}", "⚠️  Verification only applies to backups in a backup directory.".yellow());
+            return Ok(());
+        }
+
+        let backup_dir = PathBuf::from(shellexpand::tilde(&self.config.backup.dir).into_owned());
+        let mut selected: Vec<(String, PathBuf)> = if self.config.backup.versioning {
+            versions::list(&backup_dir)
+                .await?
+                .into_iter()
+                .map(|version| (version.name, version.path))
+                .collect()
+        } else if backup_dir.exists() {
+            vec![("current".to_string(), backup_dir.clone())]
+        } else {
+            Vec::new()
+        };
+        if let Some(requested) = &self.version {
+            match requested.as_str() {
+                "latest" => selected.truncate(1),
+                _ => selected.retain(|(name, _)| name == requested),
+            }
+            if selected.is_empty() {
+                return Err(format!("Backup version '{}' not found", requested).into());
+            }
+        }
+        if selected.is_empty() {
+            println!("{}", "⚠️  No backup versions found.".yellow());
+            return Ok(());
+        }
+
+        let mut damaged_versions = 0;
+        let mut damaged_items = 0;
+        let mut unverified = 0;
+        println!("\n{}:", "🔎 Verifying backups".bright_blue().bold());
+        for (name, path) in selected {
+            let manifest_path = manifest::for_version(&path);
+            if !manifest_path.exists() {
+                println!("  {} {} {}", "⚠️".bold(), name.bright_white(), "(no manifest, cannot be verified)".bright_black());
+                unverified += 1;
+                continue;
+            }
+            let items = manifest::read(&manifest_path)
+                .map_err(|e| format!("Failed to read manifest {}: {}", manifest_path.display(), e))?
+                .items;
+
+            // Items without a checksum cannot be verified, so they are not read either
+            let names: Vec<PathBuf> = items
+                .iter()
+                .filter(|item| !item.checksum.is_empty())
+                .map(|item| item.backup_path.clone())
+                .collect();
+            let version_path = path.clone();
+            let stored = tokio::task::spawn_blocking(move || versions::digests(&version_path, &names))
+                .await?
+                .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+
+            let problems: Vec<(PathBuf, &str)> = items
+                .iter()
+                .filter_map(|item| match stored.get(&item.backup_path) {
+                    _ if item.checksum.is_empty() => Some((item.backup_path.clone(), "no checksum recorded")),
+                    None => Some((item.backup_path.clone(), "missing")),
+                    Some(digest) if digest.checksum != item.checksum => Some((item.backup_path.clone(), "checksum mismatch")),
+                    Some(_) => None,
+                })
+                .collect();
+
+            if problems.is_empty() {
+                println!(
+                    "  {} {} {}",
+                    "✅".bold(),
+                    name.bright_white(),
+                    format!("({} item(s) intact)", items.len()).bright_black()
+                );
+                continue;
+            }
+
+            println!(
+                "  {} {} {}",
+                "❌".bold(),
+                name.bright_white(),
+                format!("({} of {} item(s) damaged)", problems.len(), items.len()).red()
+            );
+            for (backup_path, problem) in &problems {
+                println!(
+                    "    {} {} {}",
+                    "→".bright_black(),
+                    backup_path.display().to_string().bright_white(),
+                    format!("({})", problem).red()
+                );
+            }
+            damaged_versions += 1;
+            damaged_items += problems.len();
+        }
+
+        if unverified > 0 {
+            println!(
+                "{}",
+                format!("ℹ️  {} version(s) predate manifests and were not checked.", unverified).bright_cyan()
+            );
+        }
+        if damaged_items > 0 {
+            return Err(format!(
+                "{} damaged item(s) found in {} backup version(s)",
+                damaged_items, damaged_versions
+            )
+            .into());
+        }
+        println!("{}", "✅ All verified backups are intact.".green());
+        Ok(())
+    }
+}